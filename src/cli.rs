@@ -1,29 +1,62 @@
-use clap::Parser;
-use std::path::PathBuf;
+use crate::config::{Format, LineEnding, PathSeparator};
+use crate::index::IndexFormat;
+use crate::types::{MediaType, TreatAs};
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
 
 /// Generate m3u playlists for multi-disc ROM collections
 #[derive(Parser, Debug)]
-#[command(name = "m3u-emu", version, about)]
+#[command(
+    name = "m3u-emu",
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Directory to search for ROM media files
-    #[arg(required = true)]
-    pub target: PathBuf,
+    #[arg(required_unless_present = "print_config")]
+    pub target: Option<PathBuf>,
 
     /// Where to write m3u files (default: alongside ROMs)
     #[arg()]
     pub destination: Option<PathBuf>,
 
-    /// Use relative paths in m3u files (default: absolute)
-    #[arg(short, long)]
-    pub relative: bool,
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    /// Also write an index of all created playlists into DESTINATION (or TARGET)
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "m3u"
+    )]
+    pub index: Option<IndexFormat>,
+
+    /// Print the effective output options and exit
+    #[arg(long)]
+    pub print_config: bool,
 
     /// Create subdirectories in DESTINATION mirroring TARGET's top-level folders
     #[arg(short, long)]
     pub children: bool,
 
-    /// Use disc-style grouping for floppy formats too
-    #[arg(short, long)]
-    pub force: bool,
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    /// Redump-style DAT file listing the discs of each game
+    #[arg(long, value_name = "FILE")]
+    pub dat: Option<PathBuf>,
+
+    /// Warn about games missing discs according to the --dat file
+    #[arg(long, requires = "dat")]
+    pub verify_completeness: bool,
 
     /// Suppress progress output, only show errors
     #[arg(short, long, conflicts_with = "verbose")]
@@ -34,25 +67,207 @@ pub struct Cli {
     pub verbose: bool,
 }
 
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Report games missing discs according to a DAT, without writing m3u files
+    Check {
+        /// Directory to search for ROM media files
+        target: PathBuf,
+
+        /// Redump-style DAT file listing the discs of each game
+        #[arg(long, value_name = "FILE", required = true)]
+        dat: PathBuf,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+    },
+
+    /// Explain how the files in one directory are parsed and grouped
+    Doctor {
+        /// Directory to examine (not searched recursively)
+        dir: PathBuf,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Where a run would write m3u files (default: alongside the ROMs)
+        destination: Option<PathBuf>,
+
+        /// Explain a --children run over TARGET, which must contain DIR
+        #[arg(short, long, value_name = "TARGET", requires = "destination")]
+        children: Option<PathBuf>,
+
+        #[command(flatten)]
+        scan: ScanArgs,
+
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+}
+
+/// Options deciding how m3u entries are written, merged over --format
+#[derive(Args, Debug)]
+pub struct OutputArgs {
+    /// Use relative paths in m3u files (default: absolute)
+    #[arg(short, long)]
+    pub relative: bool,
+
+    /// Use paths relative to PATH in m3u files instead of the m3u's directory
+    #[arg(long, value_name = "PATH", conflicts_with = "relative")]
+    pub relative_to: Option<PathBuf>,
+
+    /// Use absolute paths in m3u files, even if --format defaults to relative
+    #[arg(long, conflicts_with_all = ["relative", "relative_to"])]
+    pub absolute: bool,
+
+    /// Frontend preset setting defaults for the path, label and line ending options
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub format: Option<Format>,
+
+    /// Path separator for m3u entries [default: native]
+    #[arg(long, value_enum, value_name = "SEP")]
+    pub path_separator: Option<PathSeparator>,
+
+    /// Line ending for m3u files [default: lf]
+    #[arg(long, value_enum, value_name = "EOL")]
+    pub line_ending: Option<LineEnding>,
+
+    /// Append a "|label" naming the disc to each m3u entry
+    #[arg(long)]
+    pub labels: bool,
+
+    /// Don't label m3u entries, even if --format defaults to labels
+    #[arg(long, conflicts_with = "labels")]
+    pub no_labels: bool,
+}
+
+/// Options deciding which files are picked up and how they are grouped,
+/// shared by runs and subcommands so they all see the same playlists
+#[derive(Args, Debug)]
+pub struct ScanArgs {
+    /// Use disc-style grouping for floppy formats too
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// How to group files into playlists, regardless of their media type
+    #[arg(long, value_enum, value_name = "MODE", default_value_t, conflicts_with = "force")]
+    pub treat_as: TreatAs,
+
+    /// Ignore files with this extension (repeatable, case-insensitive)
+    #[arg(long = "exclude-ext", value_name = "EXT", value_parser = parse_extension)]
+    pub exclude_ext: Vec<String>,
+}
+
+impl ScanArgs {
+    /// The grouping mode for the run, with --force meaning disc style
+    pub fn grouping_mode(&self) -> TreatAs {
+        if self.force {
+            TreatAs::Disc
+        } else {
+            self.treat_as
+        }
+    }
+
+    /// Non-fatal problems with the options, to be reported as warnings
+    pub fn warnings(&self) -> Vec<String> {
+        self.exclude_ext
+            .iter()
+            .filter(|ext| MediaType::from_extension(ext).is_none())
+            .map(|ext| format!("--exclude-ext {} is not a known media extension", ext))
+            .collect()
+    }
+}
+
 impl Cli {
     pub fn validate(&self) -> Result<(), String> {
-        if self.children && self.destination.is_none() {
-            return Err("the --children flag requires a DESTINATION".to_string());
+        match &self.command {
+            Some(Command::Check { target, dat, .. }) => {
+                validate_target(target)?;
+                validate_dat(dat)
+            }
+            Some(Command::Doctor { dir, children, .. }) => {
+                validate_target(dir)?;
+                if let Some(target) = children {
+                    validate_target(target)?;
+                    let (dir, target) = (absolute(dir), absolute(target));
+                    if dir.strip_prefix(&target).map_or(true, |p| p.as_os_str().is_empty()) {
+                        return Err(format!(
+                            "{} is not inside the --children target {}",
+                            dir.display(),
+                            target.display()
+                        ));
+                    }
+                }
+                Ok(())
+            }
+            None if self.print_config => Ok(()),
+            None => {
+                if self.children && self.destination.is_none() {
+                    return Err("the --children flag requires a DESTINATION".to_string());
+                }
+                validate_target(self.target())?;
+                if let Some(dat) = &self.dat {
+                    validate_dat(dat)?;
+                }
+                Ok(())
+            }
         }
-        if !self.target.exists() {
-            return Err(format!(
-                "target directory does not exist: {}",
-                self.target.display()
-            ));
+    }
+
+    /// The scan options of the run or subcommand
+    pub fn scan(&self) -> &ScanArgs {
+        match &self.command {
+            Some(Command::Check { scan, .. }) | Some(Command::Doctor { scan, .. }) => scan,
+            None => &self.scan,
         }
-        if !self.target.is_dir() {
-            return Err(format!(
-                "target is not a directory: {}",
-                self.target.display()
-            ));
+    }
+
+    /// Non-fatal problems with the arguments, to be reported as warnings
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = self.scan().warnings();
+        if self.dat.is_some() && !self.verify_completeness && self.command.is_none() {
+            warnings.push("--dat has no effect without --verify-completeness".to_string());
         }
-        Ok(())
+        warnings
+    }
+
+    /// The target directory; always present when no subcommand is given
+    pub fn target(&self) -> &Path {
+        self.target.as_deref().expect("required unless a subcommand is given")
+    }
+}
+
+/// Normalize an extension argument: no leading dot, lowercase
+fn parse_extension(s: &str) -> Result<String, String> {
+    Ok(s.trim_start_matches('.').to_lowercase())
+}
+
+fn validate_target(target: &Path) -> Result<(), String> {
+    if !target.exists() {
+        return Err(format!(
+            "target directory does not exist: {}",
+            target.display()
+        ));
+    }
+    if !target.is_dir() {
+        return Err(format!(
+            "target is not a directory: {}",
+            target.display()
+        ));
+    }
+    Ok(())
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn validate_dat(dat: &Path) -> Result<(), String> {
+    if !dat.is_file() {
+        return Err(format!("DAT file does not exist: {}", dat.display()));
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -62,11 +277,13 @@ mod tests {
     #[test]
     fn test_cli_parse_basic() {
         let cli = Cli::parse_from(["m3u-emu", "/some/path"]);
-        assert_eq!(cli.target, PathBuf::from("/some/path"));
+        assert_eq!(cli.target, Some(PathBuf::from("/some/path")));
+        assert!(cli.command.is_none());
         assert!(cli.destination.is_none());
-        assert!(!cli.relative);
+        assert!(!cli.output.relative);
         assert!(!cli.children);
-        assert!(!cli.force);
+        assert!(!cli.scan.force);
+        assert!(cli.output.relative_to.is_none());
     }
 
     #[test]
@@ -74,9 +291,9 @@ mod tests {
         let cli = Cli::parse_from([
             "m3u-emu", "-r", "-c", "-f", "-q", "/target", "/dest"
         ]);
-        assert!(cli.relative);
+        assert!(cli.output.relative);
         assert!(cli.children);
-        assert!(cli.force);
+        assert!(cli.scan.force);
         assert!(cli.quiet);
         assert_eq!(cli.destination, Some(PathBuf::from("/dest")));
     }
@@ -86,7 +303,141 @@ mod tests {
         let cli = Cli::parse_from([
             "m3u-emu", "--relative", "--verbose", "/target"
         ]);
-        assert!(cli.relative);
+        assert!(cli.output.relative);
         assert!(cli.verbose);
     }
+
+    #[test]
+    fn test_cli_parse_relative_to() {
+        let cli = Cli::parse_from([
+            "m3u-emu", "--relative-to", "/roms", "/target", "/dest"
+        ]);
+        assert_eq!(cli.output.relative_to, Some(PathBuf::from("/roms")));
+        assert!(!cli.output.relative);
+    }
+
+    #[test]
+    fn test_cli_parse_verify_completeness() {
+        let cli = Cli::parse_from([
+            "m3u-emu", "--dat", "redump.dat", "--verify-completeness", "/target"
+        ]);
+        assert_eq!(cli.dat, Some(PathBuf::from("redump.dat")));
+        assert!(cli.verify_completeness);
+
+        let result = Cli::try_parse_from(["m3u-emu", "--verify-completeness", "/target"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_dat_without_verify_warns() {
+        let cli = Cli::parse_from(["m3u-emu", "--dat", "redump.dat", "/target"]);
+        let warnings = cli.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("--verify-completeness"));
+    }
+
+    #[test]
+    fn test_cli_parse_check() {
+        let cli = Cli::parse_from([
+            "m3u-emu", "check", "--dat", "redump.dat", "--treat-as", "floppy",
+            "--exclude-ext", "chd", "/target",
+        ]);
+        assert!(cli.target.is_none());
+        assert_eq!(cli.scan().grouping_mode(), TreatAs::Floppy);
+        match cli.command {
+            Some(Command::Check { target, dat, scan }) => {
+                assert_eq!(target, PathBuf::from("/target"));
+                assert_eq!(dat, PathBuf::from("redump.dat"));
+                assert_eq!(scan.exclude_ext, vec!["chd".to_string()]);
+            }
+            _ => panic!("expected check subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_doctor() {
+        let cli = Cli::parse_from([
+            "m3u-emu", "doctor", "--json", "--treat-as", "floppy", "--relative", "/dir", "/dest",
+        ]);
+        match cli.command {
+            Some(Command::Doctor { dir, destination, json, children, scan, output }) => {
+                assert_eq!(dir, PathBuf::from("/dir"));
+                assert_eq!(destination, Some(PathBuf::from("/dest")));
+                assert!(json);
+                assert!(children.is_none());
+                assert_eq!(scan.grouping_mode(), TreatAs::Floppy);
+                assert!(scan.exclude_ext.is_empty());
+                assert!(output.relative);
+            }
+            _ => panic!("expected doctor subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_exclude_ext() {
+        let cli = Cli::parse_from([
+            "m3u-emu", "--exclude-ext", "CHD", "--exclude-ext", ".cue", "/target"
+        ]);
+        assert_eq!(cli.scan.exclude_ext, vec!["chd".to_string(), "cue".to_string()]);
+        assert!(cli.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_cli_exclude_ext_unknown_warns() {
+        let cli = Cli::parse_from(["m3u-emu", "--exclude-ext", "chdd", "/target"]);
+        let warnings = cli.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("chdd"));
+    }
+
+    #[test]
+    fn test_cli_parse_treat_as() {
+        let cli = Cli::parse_from(["m3u-emu", "/target"]);
+        assert_eq!(cli.scan.grouping_mode(), TreatAs::Auto);
+
+        let cli = Cli::parse_from(["m3u-emu", "--treat-as", "floppy", "/target"]);
+        assert_eq!(cli.scan.grouping_mode(), TreatAs::Floppy);
+
+        let cli = Cli::parse_from(["m3u-emu", "-f", "/target"]);
+        assert_eq!(cli.scan.grouping_mode(), TreatAs::Disc);
+
+        let result = Cli::try_parse_from(["m3u-emu", "-f", "--treat-as", "floppy", "/target"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_print_config_without_target() {
+        let cli = Cli::parse_from(["m3u-emu", "--format", "retroarch", "--print-config"]);
+        assert!(cli.print_config);
+        assert!(cli.target.is_none());
+        assert_eq!(cli.output.format, Some(Format::Retroarch));
+        assert!(cli.validate().is_ok());
+    }
+
+    #[test]
+    fn test_cli_parse_index() {
+        let cli = Cli::parse_from(["m3u-emu", "/target"]);
+        assert!(cli.index.is_none());
+
+        let cli = Cli::parse_from(["m3u-emu", "--index", "/target"]);
+        assert_eq!(cli.index, Some(IndexFormat::M3u));
+        assert_eq!(cli.target, Some(PathBuf::from("/target")));
+
+        let cli = Cli::parse_from(["m3u-emu", "--index=csv", "/target"]);
+        assert_eq!(cli.index, Some(IndexFormat::Csv));
+    }
+
+    #[test]
+    fn test_cli_doctor_children_requires_destination() {
+        let result = Cli::try_parse_from(["m3u-emu", "doctor", "--children", "/roms", "/roms/psx"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_relative_to_conflicts_with_relative() {
+        let result = Cli::try_parse_from([
+            "m3u-emu", "--relative", "--relative-to", "/roms", "/target"
+        ]);
+        assert!(result.is_err());
+    }
 }
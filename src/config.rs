@@ -0,0 +1,308 @@
+// Config module merging output flags over --format presets
+
+use crate::cli::OutputArgs;
+use crate::m3u::{normalize_path, Anchor};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Frontend presets, each a set of defaults for the output options
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    /// Absolute paths, native separators, LF line endings
+    #[default]
+    Generic,
+    /// Paths relative to the m3u (bare filenames when co-located), pipe labels,
+    /// LF line endings
+    Retroarch,
+    /// Absolute paths, backslash separators, CRLF line endings
+    WindowsFrontend,
+}
+
+/// Path separator used for m3u entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum PathSeparator {
+    /// Whatever the platform produces
+    #[default]
+    Native,
+    /// Forward slashes
+    Slash,
+    /// Backslashes
+    Backslash,
+}
+
+/// Line ending used in m3u files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// How entries are anchored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathMode {
+    Absolute,
+    /// Relative to the directory of the m3u file
+    Relative,
+    /// Relative to a fixed base directory
+    RelativeTo(PathBuf),
+}
+
+impl PathMode {
+    /// How the entries of an m3u in `m3u_dir` are anchored
+    pub fn anchor<'a>(&'a self, m3u_dir: &'a Path) -> Anchor<'a> {
+        match self {
+            PathMode::Absolute => Anchor::Absolute,
+            PathMode::Relative => Anchor::M3uDir(m3u_dir),
+            PathMode::RelativeTo(base) => Anchor::Base(base),
+        }
+    }
+}
+
+/// Formatting of the entries inside an m3u file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EntryStyle {
+    pub path_separator: PathSeparator,
+    pub line_ending: LineEnding,
+    /// Append "|label" to each entry, as RetroArch shows in its disc menu
+    pub labels: bool,
+}
+
+impl EntryStyle {
+    /// Rewrite the separators of an entry according to the style
+    pub fn format_path(&self, path: String) -> String {
+        match self.path_separator {
+            PathSeparator::Native => path,
+            PathSeparator::Slash => path.replace('\\', "/"),
+            PathSeparator::Backslash => path.replace('/', "\\"),
+        }
+    }
+
+    /// The line terminator to write after each entry
+    pub fn eol(&self) -> &'static str {
+        match self.line_ending {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Defaults a --format preset supplies; `None` keeps the generic default
+#[derive(Debug, Default)]
+struct Preset {
+    relative: Option<bool>,
+    labels: Option<bool>,
+    path_separator: Option<PathSeparator>,
+    line_ending: Option<LineEnding>,
+}
+
+impl Format {
+    fn preset(self) -> Preset {
+        match self {
+            Format::Generic => Preset::default(),
+            Format::Retroarch => Preset {
+                relative: Some(true),
+                labels: Some(true),
+                path_separator: None,
+                line_ending: Some(LineEnding::Lf),
+            },
+            Format::WindowsFrontend => Preset {
+                relative: Some(false),
+                labels: None,
+                path_separator: Some(PathSeparator::Backslash),
+                line_ending: Some(LineEnding::Crlf),
+            },
+        }
+    }
+}
+
+/// Where an effective value came from, for --print-config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    Format,
+    Flag,
+}
+
+/// Where each effective output option came from
+#[derive(Debug, Clone, Copy)]
+struct Sources {
+    paths: Source,
+    path_separator: Source,
+    line_ending: Source,
+    labels: Source,
+}
+
+/// The output options for a run, after merging flags over the preset
+#[derive(Debug)]
+pub struct WriteConfig {
+    pub format: Format,
+    pub paths: PathMode,
+    pub style: EntryStyle,
+    sources: Sources,
+}
+
+impl WriteConfig {
+    /// Merge: explicit flags win over the --format preset, which wins over defaults
+    pub fn from_args(args: &OutputArgs) -> Result<WriteConfig> {
+        let format = args.format.unwrap_or_default();
+        let preset = format.preset();
+
+        let (paths, paths_source) = if let Some(base) = &args.relative_to {
+            let base = std::path::absolute(base).context("Failed to resolve --relative-to path")?;
+            let base = normalize_path(&base);
+            (PathMode::RelativeTo(base), Source::Flag)
+        } else if args.relative {
+            (PathMode::Relative, Source::Flag)
+        } else if args.absolute {
+            (PathMode::Absolute, Source::Flag)
+        } else {
+            match preset.relative {
+                Some(true) => (PathMode::Relative, Source::Format),
+                Some(false) => (PathMode::Absolute, Source::Format),
+                None => (PathMode::Absolute, Source::Default),
+            }
+        };
+
+        let (path_separator, separator_source) = merge(args.path_separator, preset.path_separator);
+        let (line_ending, line_ending_source) = merge(args.line_ending, preset.line_ending);
+        let labels_flag = match (args.labels, args.no_labels) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+        let (labels, labels_source) = merge(labels_flag, preset.labels);
+
+        Ok(WriteConfig {
+            format,
+            paths,
+            style: EntryStyle {
+                path_separator,
+                line_ending,
+                labels,
+            },
+            sources: Sources {
+                paths: paths_source,
+                path_separator: separator_source,
+                line_ending: line_ending_source,
+                labels: labels_source,
+            },
+        })
+    }
+}
+
+fn merge<T: Default>(flag: Option<T>, preset: Option<T>) -> (T, Source) {
+    match (flag, preset) {
+        (Some(v), _) => (v, Source::Flag),
+        (None, Some(v)) => (v, Source::Format),
+        (None, None) => (T::default(), Source::Default),
+    }
+}
+
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_string())
+        .unwrap_or_default()
+}
+
+impl fmt::Display for WriteConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = value_name(self.format);
+        let source = |s: Source| match s {
+            Source::Default => "default".to_string(),
+            Source::Format => format!("--format {}", format),
+            Source::Flag => "flag".to_string(),
+        };
+
+        let paths = match &self.paths {
+            PathMode::Absolute => "absolute".to_string(),
+            PathMode::Relative => "relative".to_string(),
+            PathMode::RelativeTo(base) => format!("relative to {}", base.display()),
+        };
+
+        writeln!(f, "format = {}", format)?;
+        writeln!(f, "paths = {}  ({})", paths, source(self.sources.paths))?;
+        writeln!(
+            f,
+            "path-separator = {}  ({})",
+            value_name(self.style.path_separator),
+            source(self.sources.path_separator)
+        )?;
+        writeln!(
+            f,
+            "line-ending = {}  ({})",
+            value_name(self.style.line_ending),
+            source(self.sources.line_ending)
+        )?;
+        writeln!(
+            f,
+            "labels = {}  ({})",
+            if self.style.labels { "on" } else { "off" },
+            source(self.sources.labels)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+
+    fn config(args: &[&str]) -> WriteConfig {
+        let cli = Cli::parse_from(std::iter::once("m3u-emu").chain(args.iter().copied()));
+        WriteConfig::from_args(&cli.output).unwrap()
+    }
+
+    #[test]
+    fn test_generic_defaults() {
+        let config = config(&["/target"]);
+        assert_eq!(config.format, Format::Generic);
+        assert_eq!(config.paths, PathMode::Absolute);
+        assert_eq!(config.style, EntryStyle::default());
+    }
+
+    #[test]
+    fn test_retroarch_preset() {
+        let config = config(&["--format", "retroarch", "/target"]);
+        assert_eq!(config.paths, PathMode::Relative);
+        assert_eq!(config.style.line_ending, LineEnding::Lf);
+        assert_eq!(config.style.path_separator, PathSeparator::Native);
+        assert!(config.style.labels);
+    }
+
+    #[test]
+    fn test_windows_frontend_preset() {
+        let config = config(&["--format", "windows-frontend", "/target"]);
+        assert_eq!(config.paths, PathMode::Absolute);
+        assert_eq!(config.style.path_separator, PathSeparator::Backslash);
+        assert_eq!(config.style.line_ending, LineEnding::Crlf);
+    }
+
+    #[test]
+    fn test_flags_override_preset() {
+        let config = config(&[
+            "--format", "windows-frontend", "--line-ending", "lf", "--relative", "/target",
+        ]);
+        assert_eq!(config.paths, PathMode::Relative);
+        assert_eq!(config.style.path_separator, PathSeparator::Backslash);
+        assert_eq!(config.style.line_ending, LineEnding::Lf);
+
+        let config = self::config(&["--format", "retroarch", "--absolute", "--no-labels", "/target"]);
+        assert_eq!(config.paths, PathMode::Absolute);
+        assert!(!config.style.labels);
+    }
+
+    #[test]
+    fn test_display_shows_sources() {
+        let text = config(&["--format", "retroarch", "--path-separator", "slash", "/target"]).to_string();
+        assert!(text.contains("format = retroarch"));
+        assert!(text.contains("paths = relative  (--format retroarch)"));
+        assert!(text.contains("path-separator = slash  (flag)"));
+        assert!(text.contains("line-ending = lf  (--format retroarch)"));
+        assert!(text.contains("labels = on  (--format retroarch)"));
+    }
+}
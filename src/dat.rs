@@ -0,0 +1,401 @@
+// DAT module for checking game sets against Redump-style DAT files
+
+use crate::m3u::GameSet;
+use crate::parser::parse_name;
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+static GAME_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?s)<game\s[^>]*?name="([^"]*)"[^>]*>(.*?)</game>"#).unwrap()
+});
+
+static ROM_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"<rom\s([^>]*?)/?>").unwrap());
+
+static ATTR_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#"(\w+)="([^"]*)""#).unwrap());
+
+/// A single file listed for a disc in the DAT
+#[derive(Debug, Clone)]
+pub struct DatRom {
+    pub size: Option<u64>,
+    pub crc: Option<u32>,
+}
+
+/// One disc of a game, as listed in the DAT
+#[derive(Debug, Clone)]
+pub struct DatDisc {
+    /// Full DAT entry name, e.g. "Riven (USA) (Disc 2)"
+    pub title: String,
+    pub disc_number: f32,
+    pub roms: Vec<DatRom>,
+}
+
+/// A game with all its discs, grouped by canonical name
+#[derive(Debug, Clone)]
+pub struct DatGame {
+    /// Name with disc markers removed, comparable to `GameSet::name`
+    pub name: String,
+    pub discs: Vec<DatDisc>,
+}
+
+/// A parsed DAT file
+#[derive(Debug, Default)]
+pub struct Dat {
+    /// Games in the order the DAT lists them
+    pub games: Vec<DatGame>,
+    /// Index into `games` by lowercased canonical name
+    by_name: HashMap<String, usize>,
+    /// (game, disc, crc) of every ROM with a CRC, by size
+    by_size: HashMap<u64, Vec<(usize, usize, u32)>>,
+}
+
+/// Discs of a DAT game that were not found in a game set
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissingDiscs {
+    pub game: String,
+    pub expected: usize,
+    pub found: usize,
+    /// DAT titles of the missing discs
+    pub missing: Vec<String>,
+}
+
+impl fmt::Display for MissingDiscs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: found {} of {} discs, missing {}",
+            self.game,
+            self.found,
+            self.expected,
+            self.missing.join(", ")
+        )
+    }
+}
+
+impl Dat {
+    /// Load and parse a DAT file
+    pub fn load(path: &Path) -> Result<Dat> {
+        let xml = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read DAT {}", path.display()))?;
+        Ok(Dat::parse(&xml))
+    }
+
+    /// Parse DAT XML, grouping per-disc entries into games
+    pub fn parse(xml: &str) -> Dat {
+        let mut games: Vec<DatGame> = Vec::new();
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+
+        for cap in GAME_REGEX.captures_iter(xml) {
+            let title = unescape(&cap[1]);
+            let roms = ROM_REGEX
+                .captures_iter(&cap[2])
+                .map(|rom| {
+                    let attrs: HashMap<_, _> = ATTR_REGEX
+                        .captures_iter(&rom[1])
+                        .map(|a| (a[1].to_lowercase(), a[2].to_string()))
+                        .collect();
+                    DatRom {
+                        size: attrs.get("size").and_then(|s| s.parse().ok()),
+                        crc: attrs.get("crc").and_then(|c| u32::from_str_radix(c, 16).ok()),
+                    }
+                })
+                .collect();
+
+            let parsed = parse_name(&title);
+            let disc = DatDisc {
+                title,
+                disc_number: parsed.disc_number,
+                roms,
+            };
+
+            match by_name.entry(parsed.base_name.to_ascii_lowercase()) {
+                Entry::Occupied(e) => games[*e.get()].discs.push(disc),
+                Entry::Vacant(e) => {
+                    e.insert(games.len());
+                    games.push(DatGame {
+                        name: parsed.base_name,
+                        discs: vec![disc],
+                    });
+                }
+            }
+        }
+
+        let mut by_size: HashMap<u64, Vec<(usize, usize, u32)>> = HashMap::new();
+        for (game, g) in games.iter().enumerate() {
+            for (disc, d) in g.discs.iter().enumerate() {
+                for rom in &d.roms {
+                    if let (Some(size), Some(crc)) = (rom.size, rom.crc) {
+                        by_size.entry(size).or_default().push((game, disc, crc));
+                    }
+                }
+            }
+        }
+
+        Dat {
+            games,
+            by_name,
+            by_size,
+        }
+    }
+
+    /// The DAT games a group belongs to, each with the disc it holds if that
+    /// could be identified. A group is matched by canonical name and its
+    /// discs by number; otherwise each file is matched to a disc by its hash.
+    fn match_group(&self, group: &GameSet) -> Vec<(usize, Option<usize>)> {
+        if let Some(&game) = self.by_name.get(&group.name.to_ascii_lowercase()) {
+            let discs = self.games[game]
+                .discs
+                .iter()
+                .enumerate()
+                .filter(|(_, d)| group.files.iter().any(|f| f.disc_number == d.disc_number))
+                .map(|(disc, _)| (game, Some(disc)));
+            return std::iter::once((game, None)).chain(discs).collect();
+        }
+
+        group
+            .files
+            .iter()
+            .filter_map(|f| self.find_disc_by_hash(&f.path))
+            .map(|(game, disc)| (game, Some(disc)))
+            .collect()
+    }
+
+    /// Find the DAT game and disc with a ROM matching a file's size and CRC
+    fn find_disc_by_hash(&self, path: &Path) -> Option<(usize, usize)> {
+        // Only hash files whose size matches something in the DAT
+        let size = fs::metadata(path).ok()?.len();
+        let candidates = self.by_size.get(&size)?;
+
+        let crc = crc32_file(path).ok()?;
+        candidates
+            .iter()
+            .find(|&&(_, _, c)| c == crc)
+            .map(|&(game, disc, _)| (game, disc))
+    }
+}
+
+/// Compare groups against the DAT, returning the missing discs of each
+/// incomplete game. Groups resolving to the same DAT game are combined, and
+/// groups that don't match a DAT game are ignored.
+pub fn check_completeness(dat: &Dat, groups: &[GameSet]) -> Vec<MissingDiscs> {
+    // DAT game index and which of its discs were found, in the order seen
+    let mut found: Vec<(usize, Vec<bool>)> = Vec::new();
+
+    for group in groups {
+        for (game, disc) in dat.match_group(group) {
+            let i = match found.iter().position(|(g, _)| *g == game) {
+                Some(i) => i,
+                None => {
+                    found.push((game, vec![false; dat.games[game].discs.len()]));
+                    found.len() - 1
+                }
+            };
+            if let Some(disc) = disc {
+                found[i].1[disc] = true;
+            }
+        }
+    }
+
+    found
+        .into_iter()
+        .filter_map(|(game, discs)| {
+            let game = &dat.games[game];
+            let missing: Vec<String> = game
+                .discs
+                .iter()
+                .zip(&discs)
+                .filter(|(_, &found)| !found)
+                .map(|(d, _)| d.title.clone())
+                .collect();
+
+            if missing.is_empty() {
+                return None;
+            }
+
+            Some(MissingDiscs {
+                game: game.name.clone(),
+                expected: game.discs.len(),
+                found: game.discs.len() - missing.len(),
+                missing,
+            })
+        })
+        .collect()
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Compute the CRC-32 (IEEE) of a file's contents
+fn crc32_file(path: &Path) -> Result<u32> {
+    static TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+        let mut table = [0u32; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut c = i as u32;
+            for _ in 0..8 {
+                c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+        table
+    });
+
+    let mut f = fs::File::open(path)?;
+    let mut buf = [0u8; 64 * 1024];
+    let mut crc = !0u32;
+
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for &b in &buf[..n] {
+            crc = TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+    }
+
+    Ok(!crc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MediaFile, MediaType};
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    const RIVEN_DAT: &str = r#"<?xml version="1.0"?>
+<datafile>
+	<game name="Riven (USA) (Disc 1)">
+		<description>Riven (USA) (Disc 1)</description>
+		<rom name="Riven (USA) (Disc 1).cue" size="3" crc="352441c2"/>
+	</game>
+	<game name="Riven (USA) (Disc 2)">
+		<rom name="Riven (USA) (Disc 2).cue" size="5" crc="8587d865"/>
+	</game>
+	<game name="Riven (USA) (Disc 3)">
+		<rom name="Riven (USA) (Disc 3).cue" size="5" crc="00000000"/>
+	</game>
+	<game name="Tom &amp; Jerry (Europe)">
+		<rom name="Tom &amp; Jerry (Europe).cue" size="5" crc="00000000"/>
+	</game>
+</datafile>
+"#;
+
+    fn make_group(name: &str, discs: &[f32]) -> GameSet {
+        GameSet {
+            name: name.to_string(),
+            files: discs
+                .iter()
+                .map(|&d| MediaFile {
+                    path: PathBuf::from(format!("{} (Disc {}).cue", name, d)),
+                    filename: format!("{} (Disc {}).cue", name, d),
+                    base_name: name.to_string(),
+                    disc_number: d,
+                    media_type: MediaType::DiscIndex,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_groups_discs() {
+        let dat = Dat::parse(RIVEN_DAT);
+        assert_eq!(dat.games.len(), 2);
+        assert_eq!(dat.games[0].name, "Riven (USA)");
+        assert_eq!(dat.games[0].discs.len(), 3);
+        assert_eq!(dat.games[0].discs[0].roms[0].crc, Some(0x352441c2));
+        assert_eq!(dat.games[1].name, "Tom & Jerry (Europe)");
+    }
+
+    #[test]
+    fn test_check_completeness_missing() {
+        let dat = Dat::parse(RIVEN_DAT);
+        let group = make_group("Riven (USA)", &[1.0, 3.0]);
+
+        let missing = check_completeness(&dat, &[group]);
+        assert_eq!(missing.len(), 1);
+        let missing = &missing[0];
+        assert_eq!(missing.expected, 3);
+        assert_eq!(missing.found, 2);
+        assert_eq!(missing.missing, vec!["Riven (USA) (Disc 2)".to_string()]);
+    }
+
+    #[test]
+    fn test_check_completeness_complete() {
+        let dat = Dat::parse(RIVEN_DAT);
+        let group = make_group("riven (usa)", &[1.0, 2.0, 3.0]);
+        assert!(check_completeness(&dat, &[group]).is_empty());
+    }
+
+    #[test]
+    fn test_check_completeness_unknown_game_ignored() {
+        let dat = Dat::parse(RIVEN_DAT);
+        let group = make_group("Myst (USA)", &[1.0]);
+        assert!(check_completeness(&dat, &[group]).is_empty());
+    }
+
+    #[test]
+    fn test_check_completeness_none_found() {
+        let dat = Dat::parse(RIVEN_DAT);
+        let group = make_group("Riven (USA)", &[4.0]);
+
+        let missing = check_completeness(&dat, &[group]);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].found, 0);
+    }
+
+    fn hashed_group(dir: &Path, filename: &str, contents: &str) -> GameSet {
+        let path = dir.join(filename);
+        fs::write(&path, contents).unwrap();
+        let name = filename.trim_end_matches(".cue").to_string();
+        GameSet {
+            name: name.clone(),
+            files: vec![MediaFile {
+                path,
+                filename: filename.to_string(),
+                base_name: name,
+                disc_number: 1.0,
+                media_type: MediaType::DiscIndex,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_check_completeness_by_hash() {
+        let dir = TempDir::new().unwrap();
+        let dat = Dat::parse(RIVEN_DAT);
+
+        // crc32("abc") = 352441c2, crc32("abcde") = 8587d865
+        let groups = vec![
+            hashed_group(dir.path(), "riven1.cue", "abc"),
+            hashed_group(dir.path(), "riven2.cue", "abcde"),
+        ];
+
+        // Both groups parse as disc 1; the hashes say which disc each is
+        let missing = check_completeness(&dat, &groups);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].game, "Riven (USA)");
+        assert_eq!(missing[0].found, 2);
+        assert_eq!(missing[0].missing, vec!["Riven (USA) (Disc 3)".to_string()]);
+    }
+
+    #[test]
+    fn test_check_completeness_hash_mismatch_ignored() {
+        let dir = TempDir::new().unwrap();
+        let dat = Dat::parse(RIVEN_DAT);
+
+        let groups = vec![hashed_group(dir.path(), "myst.cue", "xyz")];
+        assert!(check_completeness(&dat, &groups).is_empty());
+    }
+}
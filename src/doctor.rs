@@ -0,0 +1,367 @@
+// Doctor module explaining how one directory is scanned, parsed and grouped
+
+use crate::cli::ScanArgs;
+use crate::config::WriteConfig;
+use crate::m3u::{floppy_mode, group_files, m3u_entries};
+use crate::overrides::{DirOverrides, OVERRIDE_FILENAME};
+use crate::parser::parse_filename;
+use crate::scanner::classify_directory;
+use crate::types::{MediaType, TreatAs};
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Everything the tool decides about a directory
+#[derive(Debug, Serialize)]
+pub struct DoctorReport {
+    pub directory: PathBuf,
+    pub files: Vec<FileReport>,
+    pub scan: ScanReport,
+    pub grouping: GroupingReport,
+    pub groups: Vec<GroupReport>,
+}
+
+/// How a single file was classified and parsed
+#[derive(Debug, Serialize)]
+pub struct FileReport {
+    pub filename: String,
+    pub media_type: Option<MediaType>,
+    pub media_type_reason: String,
+    pub base_name: String,
+    pub disc_number: f32,
+    pub disc_marker: Option<String>,
+    pub side_marker: Option<String>,
+    /// "boot" or "save" when the disc marker names a role instead of a number
+    pub role: Option<String>,
+    /// Whether the file survived the scan priority and will be grouped
+    pub selected: bool,
+}
+
+/// Outcome of the floppy > disc index > disc image priority
+#[derive(Debug, Serialize)]
+pub struct ScanReport {
+    pub selected: Option<MediaType>,
+    pub floppy: usize,
+    pub disc_index: usize,
+    pub disc_image: usize,
+}
+
+/// Which grouping mode was used, and why
+#[derive(Debug, Serialize)]
+pub struct GroupingReport {
+    pub treat_as: TreatAs,
+    pub floppy_mode: bool,
+    pub reason: String,
+}
+
+/// A playlist that would be written
+#[derive(Debug, Serialize)]
+pub struct GroupReport {
+    pub name: String,
+    pub playlist: PathBuf,
+    pub entries: Vec<String>,
+    /// Files that cannot be expressed relative to the base, written unchanged
+    pub unreachable: Vec<PathBuf>,
+}
+
+/// Explain every decision a run with the given options makes for `dir`,
+/// whose playlists go to `m3u_dir`
+pub fn diagnose(
+    dir: &Path,
+    scan_args: &ScanArgs,
+    m3u_dir: &Path,
+    config: &WriteConfig,
+) -> Result<DoctorReport> {
+    let exclude = &scan_args.exclude_ext;
+    let treat_as = scan_args.grouping_mode();
+    let scan = classify_directory(dir, exclude)?;
+    let selected_type = scan.selected_type();
+
+    let mut entries: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .collect();
+    entries.sort();
+
+    let files = entries
+        .into_iter()
+        .map(|filename| diagnose_file(filename, exclude, selected_type))
+        .collect();
+
+    let scan_report = ScanReport {
+        selected: selected_type,
+        floppy: scan.floppy.len(),
+        disc_index: scan.disc_index.len(),
+        disc_image: scan.disc_image.len(),
+    };
+
+    let (treat_as, source) = match DirOverrides::load(dir) {
+        Ok(DirOverrides { treat_as: Some(t) }) => (t, format!("set by {}", OVERRIDE_FILENAME)),
+        Ok(_) => (treat_as, "from the command line".to_string()),
+        Err(e) => (
+            treat_as,
+            format!("from the command line, invalid {} ignored: {:#}", OVERRIDE_FILENAME, e),
+        ),
+    };
+    let selected = scan.into_selected();
+    let floppy_mode = floppy_mode(&selected, treat_as);
+    let reason = match treat_as {
+        TreatAs::Auto if floppy_mode => format!("auto {}; floppy files present", source),
+        TreatAs::Auto => format!("auto {}; no floppy files present", source),
+        TreatAs::Disc | TreatAs::Floppy => source,
+    };
+
+    let anchor = config.paths.anchor(m3u_dir);
+    let groups = group_files(selected, treat_as)
+        .into_iter()
+        .map(|group| {
+            let (entries, unreachable) = m3u_entries(&group.files, anchor, &config.style);
+            GroupReport {
+                playlist: m3u_dir.join(format!("{}.m3u", group.name)),
+                entries,
+                unreachable,
+                name: group.name,
+            }
+        })
+        .collect();
+
+    Ok(DoctorReport {
+        directory: dir.to_path_buf(),
+        files,
+        scan: scan_report,
+        grouping: GroupingReport {
+            treat_as,
+            floppy_mode,
+            reason,
+        },
+        groups,
+    })
+}
+
+fn diagnose_file(filename: String, exclude: &[String], selected: Option<MediaType>) -> FileReport {
+    let ext = Path::new(&filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_string);
+
+    let (media_type, media_type_reason) = match &ext {
+        None => (None, "no extension".to_string()),
+        Some(ext) if exclude.iter().any(|x| x.eq_ignore_ascii_case(ext)) => {
+            (None, format!("extension .{} excluded by --exclude-ext", ext))
+        }
+        Some(ext) => match MediaType::from_extension(ext) {
+            Some(t) => (
+                Some(t),
+                format!("extension .{} is a {} format", ext, t.description()),
+            ),
+            None => (None, format!("extension .{} is not a media format", ext)),
+        },
+    };
+
+    let parsed = parse_filename(&filename);
+    let role = parsed.disc_marker.as_ref().and_then(|m| {
+        let inner = m.trim_start_matches('(').trim_start().to_lowercase();
+        ["boot", "save"]
+            .into_iter()
+            .find(|r| inner.starts_with(r))
+            .map(str::to_string)
+    });
+
+    FileReport {
+        selected: media_type.is_some() && media_type == selected,
+        filename,
+        media_type,
+        media_type_reason,
+        base_name: parsed.base_name,
+        disc_number: parsed.disc_number,
+        disc_marker: parsed.disc_marker,
+        side_marker: parsed.side_marker,
+        role,
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Directory: {}", self.directory.display())?;
+        writeln!(f)?;
+
+        writeln!(f, "Files:")?;
+        if self.files.is_empty() {
+            writeln!(f, "  (none)")?;
+        }
+        for file in &self.files {
+            writeln!(f, "  {}", file.filename)?;
+            let type_name = file.media_type.map(|t| t.description()).unwrap_or("none");
+            writeln!(f, "    type:   {} ({})", type_name, file.media_type_reason)?;
+            writeln!(
+                f,
+                "    parsed: base name \"{}\", disc {}",
+                file.base_name, file.disc_number
+            )?;
+            match (&file.disc_marker, &file.side_marker) {
+                (None, None) => writeln!(f, "    marker: no marker matched")?,
+                (disc, side) => {
+                    if let Some(m) = disc {
+                        writeln!(f, "    marker: disc {}", m)?;
+                    }
+                    if let Some(m) = side {
+                        writeln!(f, "    marker: side {}", m)?;
+                    }
+                }
+            }
+            if let Some(role) = &file.role {
+                writeln!(f, "    role:   {}", role)?;
+            }
+            writeln!(
+                f,
+                "    scan:   {}",
+                if file.selected { "selected" } else { "ignored" }
+            )?;
+        }
+        writeln!(f)?;
+
+        let selected = self
+            .scan
+            .selected
+            .map(|t| t.description())
+            .unwrap_or("nothing");
+        writeln!(
+            f,
+            "Scan priority (floppy > disc index > disc image): {} selected",
+            selected
+        )?;
+        writeln!(
+            f,
+            "  found {} floppy, {} disc index, {} disc image files",
+            self.scan.floppy, self.scan.disc_index, self.scan.disc_image
+        )?;
+        writeln!(
+            f,
+            "Grouping: {} ({})",
+            if self.grouping.floppy_mode { "floppy" } else { "disc" },
+            self.grouping.reason
+        )?;
+        writeln!(f)?;
+
+        writeln!(f, "Playlists:")?;
+        if self.groups.is_empty() {
+            writeln!(f, "  (none)")?;
+        }
+        for group in &self.groups {
+            writeln!(f, "  {} -> {}", group.name, group.playlist.display())?;
+            for entry in &group.entries {
+                writeln!(f, "    {}", entry)?;
+            }
+            for path in &group.unreachable {
+                writeln!(f, "    ({} cannot be made relative, written unchanged)", path.display())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::Parser;
+    use std::fs::File;
+    use tempfile::TempDir;
+
+    /// Diagnose `dir` as `m3u-emu doctor <args> <dir>` would
+    fn diagnose_with(dir: &Path, args: &[&str]) -> DoctorReport {
+        let dir_arg = dir.to_string_lossy();
+        let cli = Cli::parse_from(
+            ["m3u-emu", "doctor"]
+                .into_iter()
+                .chain(args.iter().copied())
+                .chain([dir_arg.as_ref()]),
+        );
+        match cli.command {
+            Some(crate::cli::Command::Doctor { scan, output, .. }) => {
+                let config = WriteConfig::from_args(&output).unwrap();
+                diagnose(dir, &scan, dir, &config).unwrap()
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn create_test_files(dir: &Path, names: &[&str]) {
+        for name in names {
+            File::create(dir.join(name)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_diagnose_disc_directory() {
+        let dir = TempDir::new().unwrap();
+        create_test_files(
+            dir.path(),
+            &["Game (Disc 2).cue", "Game (Disc 1).cue", "Game (Disc 1).bin", "Game.iso"],
+        );
+
+        let report = diagnose_with(dir.path(), &[]);
+        assert_eq!(report.files.len(), 4);
+        assert_eq!(report.scan.selected, Some(MediaType::DiscIndex));
+        assert_eq!(report.scan.disc_image, 1);
+        assert!(!report.grouping.floppy_mode);
+
+        let bin = report.files.iter().find(|f| f.filename.ends_with(".bin")).unwrap();
+        assert!(bin.media_type.is_none());
+        assert!(!bin.selected);
+
+        let iso = report.files.iter().find(|f| f.filename == "Game.iso").unwrap();
+        assert_eq!(iso.media_type, Some(MediaType::DiscImage));
+        assert!(iso.disc_marker.is_none());
+        assert!(!iso.selected);
+
+        assert_eq!(report.groups.len(), 1);
+        assert_eq!(report.groups[0].name, "Game");
+        assert!(report.groups[0].entries[0].ends_with("Game (Disc 1).cue"));
+        assert!(report.groups[0].entries[1].ends_with("Game (Disc 2).cue"));
+    }
+
+    #[test]
+    fn test_diagnose_roles_and_override() {
+        let dir = TempDir::new().unwrap();
+        create_test_files(dir.path(), &["Game (Boot).adf", "Game (Disk 1) (Side B).adf"]);
+        fs::write(dir.path().join(OVERRIDE_FILENAME), "treat-as = disc\n").unwrap();
+
+        let report = diagnose_with(dir.path(), &[]);
+        assert_eq!(report.grouping.treat_as, TreatAs::Disc);
+        assert!(report.grouping.reason.contains(OVERRIDE_FILENAME));
+
+        let boot = report.files.iter().find(|f| f.filename.contains("Boot")).unwrap();
+        assert_eq!(boot.role.as_deref(), Some("boot"));
+
+        let side = report.files.iter().find(|f| f.filename.contains("Side")).unwrap();
+        assert_eq!(side.side_marker.as_deref(), Some("(Side B)"));
+        assert!(side.role.is_none());
+    }
+
+    #[test]
+    fn test_diagnose_output_options() {
+        let dir = TempDir::new().unwrap();
+        create_test_files(dir.path(), &["Game (Disc 1).cue", "Game (Disc 2).cue"]);
+
+        let report = diagnose_with(dir.path(), &["--format", "windows-frontend", "--relative"]);
+        assert_eq!(report.groups[0].playlist, dir.path().join("Game.m3u"));
+        assert_eq!(report.groups[0].entries, vec!["Game (Disc 1).cue", "Game (Disc 2).cue"]);
+        assert!(report.groups[0].unreachable.is_empty());
+    }
+
+    #[test]
+    fn test_diagnose_excluded_extension() {
+        let dir = TempDir::new().unwrap();
+        create_test_files(dir.path(), &["Game.cue", "Game.iso"]);
+
+        let report = diagnose_with(dir.path(), &["--exclude-ext", "cue"]);
+        let cue = report.files.iter().find(|f| f.filename == "Game.cue").unwrap();
+        assert!(cue.media_type_reason.contains("--exclude-ext"));
+        assert_eq!(report.scan.selected, Some(MediaType::DiscImage));
+    }
+}
@@ -0,0 +1,281 @@
+// Index module for writing a catalog of all playlists created in a run
+
+use crate::config::EntryStyle;
+use anyhow::Result;
+use clap::ValueEnum;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Format of the index file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IndexFormat {
+    /// A playlist of playlists
+    M3u,
+    /// A human-readable list
+    Txt,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// First line of a csv index
+const CSV_HEADER: &str = "name,system,discs,path";
+
+/// First line of an m3u or txt index, marking it as written by this tool
+const INDEX_HEADER: &str = "# m3u-emu index";
+
+impl IndexFormat {
+    const ALL: [IndexFormat; 3] = [IndexFormat::M3u, IndexFormat::Txt, IndexFormat::Csv];
+
+    fn filename(self) -> &'static str {
+        match self {
+            IndexFormat::M3u => "index.m3u",
+            IndexFormat::Txt => "index.txt",
+            IndexFormat::Csv => "index.csv",
+        }
+    }
+
+    fn header(self) -> &'static str {
+        match self {
+            IndexFormat::M3u | IndexFormat::Txt => INDEX_HEADER,
+            IndexFormat::Csv => CSV_HEADER,
+        }
+    }
+
+    /// Whether the file at `path` is an index this tool wrote, recognised by
+    /// its first line
+    fn owns(self, path: &Path) -> Result<bool> {
+        let mut first = Vec::new();
+        BufReader::new(fs::File::open(path)?)
+            .take(256)
+            .read_until(b'\n', &mut first)?;
+        Ok(first.trim_ascii_end() == self.header().as_bytes())
+    }
+}
+
+/// Remove the indexes a previous run left in `dir`, so one is only present
+/// when this run writes it. Returns the removed paths.
+pub fn remove_indexes(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+
+    for format in IndexFormat::ALL {
+        let path = dir.join(format.filename());
+        if path.is_file() && format.owns(&path)? {
+            fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// One created playlist
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub name: String,
+    /// The --children top-level directory the game was found in
+    pub system: Option<String>,
+    pub discs: usize,
+    /// Path of the m3u file
+    pub path: PathBuf,
+}
+
+/// Write the index into `dir` and return its path. Any previous index must
+/// already be gone, see `remove_indexes`. Playlist paths are written
+/// relative to `dir`.
+pub fn write_index(
+    dir: &Path,
+    format: IndexFormat,
+    entries: &[IndexEntry],
+    style: &EntryStyle,
+) -> Result<PathBuf> {
+    let index_path = dir.join(format.filename());
+
+    // Never overwrite a game's playlist or a file this tool didn't write
+    if entries.iter().any(|e| e.path == index_path) {
+        anyhow::bail!(
+            "a game playlist is named {}, refusing to overwrite it with the index",
+            index_path.display()
+        );
+    }
+    if index_path.exists() {
+        anyhow::bail!(
+            "{} was not written by m3u-emu, refusing to overwrite it",
+            index_path.display()
+        );
+    }
+
+    let mut entries = entries.to_vec();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let eol = style.eol();
+
+    let mut f = fs::File::create(&index_path)?;
+
+    write!(f, "{}{}", format.header(), eol)?;
+
+    for entry in &entries {
+        let path = style.format_path(
+            pathdiff::diff_paths(&entry.path, dir)
+                .unwrap_or_else(|| entry.path.clone())
+                .to_string_lossy()
+                .to_string(),
+        );
+        let system = entry.system.as_deref().unwrap_or("");
+
+        match format {
+            IndexFormat::M3u => write!(f, "{}{}", path, eol)?,
+            IndexFormat::Txt => {
+                let discs = if entry.discs == 1 { "disc" } else { "discs" };
+                if system.is_empty() {
+                    write!(f, "{} ({} {}): {}{}", entry.name, entry.discs, discs, path, eol)?;
+                } else {
+                    write!(
+                        f,
+                        "{} [{}] ({} {}): {}{}",
+                        entry.name, system, entry.discs, discs, path, eol
+                    )?;
+                }
+            }
+            IndexFormat::Csv => write!(
+                f,
+                "{},{},{},{}{}",
+                csv_field(&entry.name),
+                csv_field(system),
+                entry.discs,
+                csv_field(&path),
+                eol
+            )?,
+        }
+    }
+
+    Ok(index_path)
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{LineEnding, PathSeparator};
+    use tempfile::TempDir;
+
+    fn entries(dir: &Path) -> Vec<IndexEntry> {
+        vec![
+            IndexEntry {
+                name: "Monkey Island".to_string(),
+                system: Some("amiga".to_string()),
+                discs: 4,
+                path: dir.join("amiga").join("Monkey Island.m3u"),
+            },
+            IndexEntry {
+                name: "Tom, Jerry".to_string(),
+                system: None,
+                discs: 1,
+                path: dir.join("Tom, Jerry.m3u"),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_write_index_m3u() {
+        let dir = TempDir::new().unwrap();
+        let path = write_index(
+            dir.path(),
+            IndexFormat::M3u,
+            &entries(dir.path()),
+            &EntryStyle {
+                path_separator: PathSeparator::Slash,
+                line_ending: LineEnding::Lf,
+                labels: false,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(path, dir.path().join("index.m3u"));
+        let content = fs::read_to_string(path).unwrap();
+        assert_eq!(content, "# m3u-emu index\nTom, Jerry.m3u\namiga/Monkey Island.m3u\n");
+    }
+
+    #[test]
+    fn test_write_index_txt() {
+        let dir = TempDir::new().unwrap();
+        let path = write_index(
+            dir.path(),
+            IndexFormat::Txt,
+            &entries(dir.path()),
+            &EntryStyle::default(),
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(path).unwrap();
+        assert!(content.contains("Tom, Jerry (1 disc): Tom, Jerry.m3u\n"));
+        assert!(content.contains("Monkey Island [amiga] (4 discs): "));
+    }
+
+    #[test]
+    fn test_write_index_csv() {
+        let dir = TempDir::new().unwrap();
+        let path = write_index(
+            dir.path(),
+            IndexFormat::Csv,
+            &entries(dir.path()),
+            &EntryStyle::default(),
+        )
+        .unwrap();
+
+        let content = fs::read_to_string(path).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines[0], "name,system,discs,path");
+        assert_eq!(lines[1], "\"Tom, Jerry\",,1,\"Tom, Jerry.m3u\"");
+        assert!(lines[2].starts_with("Monkey Island,amiga,4,"));
+    }
+
+    #[test]
+    fn test_write_index_name_collision() {
+        let dir = TempDir::new().unwrap();
+        let mut entries = entries(dir.path());
+        entries[1].path = dir.path().join("index.m3u");
+
+        let result = write_index(dir.path(), IndexFormat::M3u, &entries, &EntryStyle::default());
+        assert!(result.is_err());
+
+        // Other formats don't collide with a playlist
+        assert!(write_index(dir.path(), IndexFormat::Txt, &entries, &EntryStyle::default()).is_ok());
+    }
+
+    #[test]
+    fn test_remove_indexes_only_owned() {
+        let dir = TempDir::new().unwrap();
+        for format in IndexFormat::ALL {
+            write_index(dir.path(), format, &entries(dir.path()), &EntryStyle::default()).unwrap();
+        }
+        let removed = remove_indexes(dir.path()).unwrap();
+        assert_eq!(removed.len(), 3);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // A user's own index.txt and index.m3u are left alone, and not
+        // overwritten either
+        let notes = dir.path().join("index.txt");
+        fs::write(&notes, "my notes\n").unwrap();
+        let playlist = dir.path().join("index.m3u");
+        fs::write(&playlist, "# my curated list\nGame.m3u\n").unwrap();
+        assert!(remove_indexes(dir.path()).unwrap().is_empty());
+        assert!(notes.exists());
+        assert!(playlist.exists());
+        assert!(write_index(dir.path(), IndexFormat::Txt, &[], &EntryStyle::default()).is_err());
+    }
+
+    #[test]
+    fn test_csv_field_escapes_quotes() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+}
@@ -1,32 +1,59 @@
-use crate::types::MediaFile;
+use crate::config::EntryStyle;
+use crate::parser::parse_filename;
+use crate::types::{MediaFile, TreatAs};
 use anyhow::Result;
 use std::fs;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Component, Path, PathBuf};
 
 /// A group of files that will become one m3u
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GameSet {
     pub name: String,
     pub files: Vec<MediaFile>,
 }
 
+/// Whether `files` are grouped floppy style, all into one playlist
+pub fn floppy_mode(files: &[MediaFile], treat_as: TreatAs) -> bool {
+    match treat_as {
+        // Check if any file is floppy format
+        TreatAs::Auto => files.iter().any(|f| f.is_floppy()),
+        TreatAs::Disc => false,
+        TreatAs::Floppy => true,
+    }
+}
+
+/// The directory the playlists for the media in `dir` are written to:
+/// `dir` itself, `destination`, or with `children_of` a TARGET, the
+/// destination subdirectory named after `dir`'s top-level folder in it
+pub fn playlist_dir(dir: &Path, destination: Option<&Path>, children_of: Option<&Path>) -> PathBuf {
+    match (destination, children_of) {
+        (Some(dest), Some(target)) => {
+            let child = dir
+                .strip_prefix(target)
+                .ok()
+                .and_then(|p| p.components().next());
+            match child {
+                Some(child) => dest.join(child),
+                None => dest.to_path_buf(),
+            }
+        }
+        (Some(dest), None) => dest.to_path_buf(),
+        (None, _) => dir.to_path_buf(),
+    }
+}
+
 /// Group media files into game sets for m3u creation
-pub fn group_files(mut files: Vec<MediaFile>, force_disc_mode: bool) -> Vec<GameSet> {
+pub fn group_files(mut files: Vec<MediaFile>, treat_as: TreatAs) -> Vec<GameSet> {
     if files.is_empty() {
         return Vec::new();
     }
 
-    // Check if any file is floppy format
-    let has_floppy = files.iter().any(|f| f.is_floppy());
-    let use_floppy_mode = has_floppy && !force_disc_mode;
+    let use_floppy_mode = floppy_mode(&files, treat_as);
 
-    // Sort files by disc number for consistent ordering
-    files.sort_by(|a, b| {
-        a.disc_number
-            .partial_cmp(&b.disc_number)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    // Sort files by disc number, then filename, for an ordering that
+    // doesn't depend on the filesystem
+    files.sort_by(disc_order);
 
     if use_floppy_mode {
         // Floppy mode: all files in one group, use first file's base_name
@@ -50,16 +77,20 @@ pub fn group_files(mut files: Vec<MediaFile>, force_disc_mode: bool) -> Vec<Game
 
     // Sort files within each group by disc number
     for group in &mut groups {
-        group.files.sort_by(|a, b| {
-            a.disc_number
-                .partial_cmp(&b.disc_number)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        group.files.sort_by(disc_order);
     }
 
     groups
 }
 
+/// Order by disc number, with the filename breaking ties
+fn disc_order(a: &MediaFile, b: &MediaFile) -> std::cmp::Ordering {
+    a.disc_number
+        .partial_cmp(&b.disc_number)
+        .unwrap_or(std::cmp::Ordering::Equal)
+        .then_with(|| a.filename.cmp(&b.filename))
+}
+
 /// Check if a file appears to be a text file (not binary)
 pub fn is_text_file(path: &Path) -> Result<bool> {
     let bytes = fs::read(path)?;
@@ -88,34 +119,148 @@ pub fn is_text_file(path: &Path) -> Result<bool> {
     Ok(non_text_count < bytes.len() / 10)
 }
 
+/// How the entries of an m3u are anchored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Anchor<'a> {
+    /// Paths as found by the scan
+    Absolute,
+    /// Relative to the m3u's own directory, stepping out of it with `..` as needed
+    M3uDir(&'a Path),
+    /// Relative to a chosen base; files outside the base cannot be expressed
+    Base(&'a Path),
+}
+
+impl Anchor<'_> {
+    /// The directory entries are made relative to, if any
+    pub fn base(&self) -> Option<&Path> {
+        match self {
+            Anchor::Absolute => None,
+            Anchor::M3uDir(dir) => Some(dir),
+            Anchor::Base(base) => Some(base),
+        }
+    }
+}
+
+/// The lines of an m3u for the given media files
+///
+/// Also returns the paths that could not be expressed relative to the
+/// anchor; those entries are written as-is instead.
+pub fn m3u_entries(
+    files: &[MediaFile],
+    anchor: Anchor,
+    style: &EntryStyle,
+) -> (Vec<String>, Vec<PathBuf>) {
+    let mut entries = Vec::new();
+    let mut unreachable = Vec::new();
+
+    for media_file in files {
+        let rel = match anchor {
+            Anchor::Absolute => None,
+            Anchor::M3uDir(dir) => relative_path(&media_file.path, dir, false),
+            Anchor::Base(base) => relative_path(&media_file.path, base, true),
+        };
+        let path_str = match rel {
+            Some(rel) => rel.to_string_lossy().to_string(),
+            None => {
+                if anchor != Anchor::Absolute {
+                    unreachable.push(media_file.path.clone());
+                }
+                media_file.path.to_string_lossy().to_string()
+            }
+        };
+        let entry = style.format_path(path_str);
+        if style.labels {
+            entries.push(format!("{}|{}", entry, disc_label(media_file)));
+        } else {
+            entries.push(entry);
+        }
+    }
+
+    (entries, unreachable)
+}
+
+/// A short name for a disc, e.g. "Disk 2 Side B", for labelled entries.
+/// Falls back to the file name without extension when there is no marker.
+fn disc_label(file: &MediaFile) -> String {
+    let parsed = parse_filename(&file.filename);
+    let markers: Vec<&str> = [parsed.disc_marker.as_deref(), parsed.side_marker.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(|m| m.trim_start_matches('(').trim_end_matches(')').trim())
+        .collect();
+
+    if markers.is_empty() {
+        Path::new(&file.filename)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| file.filename.clone())
+    } else {
+        markers.join(" ")
+    }
+}
+
 /// Write an m3u file with paths to the given media files
+///
+/// Returns the paths that could not be expressed relative to the anchor;
+/// those entries are written as-is instead.
 pub fn write_m3u(
     m3u_path: &Path,
     files: &[MediaFile],
-    relative_to: Option<&Path>,
-) -> Result<()> {
+    anchor: Anchor,
+    style: &EntryStyle,
+) -> Result<Vec<PathBuf>> {
     let mut f = fs::File::create(m3u_path)?;
+    let (entries, unreachable) = m3u_entries(files, anchor, style);
 
-    for media_file in files {
-        let path_str = match relative_to {
-            Some(base) => {
-                // Calculate relative path
-                pathdiff::diff_paths(&media_file.path, base)
-                    .unwrap_or_else(|| media_file.path.clone())
-                    .to_string_lossy()
-                    .to_string()
-            }
-            None => media_file.path.to_string_lossy().to_string(),
-        };
-        writeln!(f, "{}", path_str)?;
+    for entry in entries {
+        write!(f, "{}{}", entry, style.eol())?;
+    }
+
+    Ok(unreachable)
+}
+
+/// Calculate the path of `path` relative to `base`, if it can be expressed.
+/// With `contained`, only paths inside `base` can be.
+fn relative_path(path: &Path, base: &Path, contained: bool) -> Option<PathBuf> {
+    // A relative media path can still be anchored at an absolute base
+    let path = if base.is_absolute() && path.is_relative() {
+        std::path::absolute(path).ok()?
+    } else {
+        path.to_path_buf()
+    };
+    let (path, base) = (normalize_path(&path), normalize_path(base));
+
+    if contained && !path.starts_with(&base) {
+        return None;
     }
 
-    Ok(())
+    pathdiff::diff_paths(&path, &base).filter(|p| p.is_relative())
+}
+
+/// Resolve `.` and `..` components lexically, without touching the filesystem
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                // ".." at the root stays at the root
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => out.push(component),
+            },
+            _ => out.push(component),
+        }
+    }
+    out
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{LineEnding, PathSeparator};
     use crate::types::MediaType;
     use std::io::Write;
     use std::path::PathBuf;
@@ -150,7 +295,7 @@ mod tests {
             make_media_file("FF8 (Disc 1).cue", "FF8", 1.0, false),
         ];
 
-        let groups = group_files(files, false);
+        let groups = group_files(files, TreatAs::Auto);
         assert_eq!(groups.len(), 2);
 
         let ff7 = groups.iter().find(|g| g.name == "FF7").unwrap();
@@ -169,7 +314,7 @@ mod tests {
         ];
 
         // In floppy mode without force, all files go into one group
-        let groups = group_files(files, false);
+        let groups = group_files(files, TreatAs::Auto);
         assert_eq!(groups.len(), 1);
         assert_eq!(groups[0].files.len(), 3);
     }
@@ -183,10 +328,37 @@ mod tests {
         ];
 
         // With force, group by name like disc mode
-        let groups = group_files(files, true);
+        let groups = group_files(files, TreatAs::Disc);
         assert_eq!(groups.len(), 2);
     }
 
+    #[test]
+    fn test_group_files_disc_as_floppy() {
+        let files = vec![
+            make_media_file("Intro.iso", "Intro", 1.0, false),
+            make_media_file("Main.iso", "Main", 1.0, false),
+        ];
+
+        let groups = group_files(files, TreatAs::Floppy);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, "Intro");
+        assert_eq!(groups[0].files.len(), 2);
+    }
+
+    #[test]
+    fn test_group_files_ties_sorted_by_filename() {
+        let files = vec![
+            make_media_file("Outro.iso", "Outro", 1.0, false),
+            make_media_file("Intro.iso", "Intro", 1.0, false),
+            make_media_file("Main Part.iso", "Main Part", 1.0, false),
+        ];
+
+        let groups = group_files(files, TreatAs::Floppy);
+        assert_eq!(groups[0].name, "Intro");
+        let names: Vec<_> = groups[0].files.iter().map(|f| f.filename.as_str()).collect();
+        assert_eq!(names, vec!["Intro.iso", "Main Part.iso", "Outro.iso"]);
+    }
+
     #[test]
     fn test_group_files_sorted() {
         let files = vec![
@@ -195,7 +367,7 @@ mod tests {
             make_media_file("Game (Disc 2).cue", "Game", 2.0, false),
         ];
 
-        let groups = group_files(files, false);
+        let groups = group_files(files, TreatAs::Auto);
         let game = &groups[0];
 
         assert_eq!(game.files[0].disc_number, 1.0);
@@ -203,6 +375,25 @@ mod tests {
         assert_eq!(game.files[2].disc_number, 3.0);
     }
 
+    #[test]
+    fn test_playlist_dir() {
+        let dir = Path::new("/roms/psx/FF7");
+        assert_eq!(playlist_dir(dir, None, None), dir);
+        assert_eq!(playlist_dir(dir, Some(Path::new("/out")), None), Path::new("/out"));
+        assert_eq!(
+            playlist_dir(dir, Some(Path::new("/out")), Some(Path::new("/roms"))),
+            Path::new("/out/psx")
+        );
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path(Path::new("/x/playlists/../roms/./psx")), Path::new("/x/roms/psx"));
+        assert_eq!(normalize_path(Path::new("/../roms")), Path::new("/roms"));
+        assert_eq!(normalize_path(Path::new("../../roms")), Path::new("../../roms"));
+        assert_eq!(normalize_path(Path::new("a/../../roms")), Path::new("../roms"));
+    }
+
     #[test]
     fn test_write_m3u_absolute() {
         let dir = TempDir::new().unwrap();
@@ -220,7 +411,7 @@ mod tests {
         ];
 
         let m3u_path = dir.path().join("Game.m3u");
-        write_m3u(&m3u_path, &files, None).unwrap();
+        write_m3u(&m3u_path, &files, Anchor::Absolute, &EntryStyle::default()).unwrap();
 
         let content = fs::read_to_string(&m3u_path).unwrap();
         assert!(content.contains(&game_dir.join("Game (Disc 1).cue").to_string_lossy().to_string()));
@@ -243,12 +434,126 @@ mod tests {
         ];
 
         let m3u_path = dir.path().join("Game.m3u");
-        write_m3u(&m3u_path, &files, Some(dir.path())).unwrap();
+        write_m3u(&m3u_path, &files, Anchor::M3uDir(dir.path()), &EntryStyle::default()).unwrap();
 
         let content = fs::read_to_string(&m3u_path).unwrap();
         assert!(content.contains("games/Game (Disc 1).cue") || content.contains("games\\Game (Disc 1).cue"));
     }
 
+    #[test]
+    fn test_write_m3u_relative_to_other_base() {
+        let dir = TempDir::new().unwrap();
+        let game_dir = dir.path().join("roms/psx");
+        let m3u_dir = dir.path().join("playlists");
+        fs::create_dir_all(&game_dir).unwrap();
+        fs::create_dir_all(&m3u_dir).unwrap();
+
+        let files = vec![
+            MediaFile {
+                path: game_dir.join("Game (Disc 1).cue"),
+                filename: "Game (Disc 1).cue".to_string(),
+                base_name: "Game".to_string(),
+                disc_number: 1.0,
+                media_type: MediaType::DiscIndex,
+            },
+        ];
+
+        let m3u_path = m3u_dir.join("Game.m3u");
+        let unreachable = write_m3u(&m3u_path, &files, Anchor::Base(&dir.path().join("roms")), &EntryStyle::default())
+                .unwrap();
+        assert!(unreachable.is_empty());
+
+        let content = fs::read_to_string(&m3u_path).unwrap();
+        assert_eq!(content.trim_end(), PathBuf::from("psx/Game (Disc 1).cue").to_string_lossy());
+
+        // A base given with ".." is the same base
+        let base = m3u_dir.join("../roms");
+        let unreachable = write_m3u(&m3u_path, &files, Anchor::Base(&base), &EntryStyle::default())
+                .unwrap();
+        assert!(unreachable.is_empty());
+
+        let content = fs::read_to_string(&m3u_path).unwrap();
+        assert_eq!(content.trim_end(), PathBuf::from("psx/Game (Disc 1).cue").to_string_lossy());
+    }
+
+    #[test]
+    fn test_write_m3u_relative_unreachable() {
+        let dir = TempDir::new().unwrap();
+        let game_dir = dir.path().join("roms/psx");
+        fs::create_dir_all(&game_dir).unwrap();
+
+        // A file outside the base cannot be reached from it
+        let file_path = game_dir.join("Game (Disc 1).cue");
+        let files = vec![
+            MediaFile {
+                path: file_path.clone(),
+                filename: "Game (Disc 1).cue".to_string(),
+                base_name: "Game".to_string(),
+                disc_number: 1.0,
+                media_type: MediaType::DiscIndex,
+            },
+        ];
+
+        let m3u_path = dir.path().join("Game.m3u");
+        let base = dir.path().join("other");
+        let unreachable = write_m3u(&m3u_path, &files, Anchor::Base(&base), &EntryStyle::default())
+                .unwrap();
+        assert_eq!(unreachable, vec![file_path.clone()]);
+
+        let content = fs::read_to_string(&m3u_path).unwrap();
+        assert_eq!(content.trim_end(), file_path.to_string_lossy());
+
+        // Relative to the m3u's directory, stepping out of it is fine
+        let m3u_dir = dir.path().join("playlists");
+        let unreachable = write_m3u(&m3u_path, &files, Anchor::M3uDir(&m3u_dir), &EntryStyle::default())
+                .unwrap();
+        assert!(unreachable.is_empty());
+
+        let content = fs::read_to_string(&m3u_path).unwrap();
+        assert_eq!(
+            content.trim_end(),
+            PathBuf::from("../roms/psx/Game (Disc 1).cue").to_string_lossy()
+        );
+    }
+
+    #[test]
+    fn test_write_m3u_style() {
+        let dir = TempDir::new().unwrap();
+        let files = vec![
+            make_media_file("games/Game (Disc 1).cue", "Game", 1.0, false),
+            make_media_file("games/Game (Disc 2).cue", "Game", 2.0, false),
+        ];
+        let style = EntryStyle {
+            path_separator: PathSeparator::Backslash,
+            line_ending: LineEnding::Crlf,
+            labels: false,
+        };
+
+        let m3u_path = dir.path().join("Game.m3u");
+        write_m3u(&m3u_path, &files, Anchor::Absolute, &style).unwrap();
+
+        let content = fs::read_to_string(&m3u_path).unwrap();
+        assert_eq!(content, "games\\Game (Disc 1).cue\r\ngames\\Game (Disc 2).cue\r\n");
+    }
+
+    #[test]
+    fn test_m3u_entries_labels() {
+        let files = vec![
+            make_media_file("Game (Disk 1) (Side B).adf", "Game", 1.2, true),
+            make_media_file("Intro.adf", "Intro", 1.0, true),
+        ];
+        let style = EntryStyle {
+            labels: true,
+            ..EntryStyle::default()
+        };
+
+        let (entries, _) = m3u_entries(&files, Anchor::Absolute, &style);
+        assert_eq!(
+            entries,
+            vec!["Game (Disk 1) (Side B).adf|Disk 1 Side B", "Intro.adf|Intro"]
+        );
+    }
+
     fn make_media_file(filename: &str, base_name: &str, disc: f32, floppy: bool) -> MediaFile {
         MediaFile {
             path: PathBuf::from(filename),
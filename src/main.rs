@@ -1,18 +1,28 @@
 mod cli;
+mod config;
+mod dat;
+mod doctor;
+mod index;
 mod m3u;
 mod output;
+mod overrides;
 mod parser;
 mod scanner;
 mod types;
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use cli::Cli;
-use m3u::{group_files, is_text_file, write_m3u};
+use cli::{Cli, Command, ScanArgs};
+use config::WriteConfig;
+use dat::{check_completeness, Dat};
+use index::{remove_indexes, write_index, IndexEntry};
+use m3u::{group_files, is_text_file, playlist_dir, write_m3u, Anchor, GameSet};
 use output::Output;
+use overrides::{DirOverrides, OVERRIDE_FILENAME};
 use scanner::scan_directory;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use types::TreatAs;
 use walkdir::WalkDir;
 
 fn main() {
@@ -24,6 +34,9 @@ fn main() {
     }
 
     let output = Output::new(cli.quiet, cli.verbose);
+    for warning in cli.warnings() {
+        output.warning(&warning);
+    }
 
     if let Err(e) = run(&cli, &output) {
         output.error(&format!("{:#}", e));
@@ -32,18 +45,67 @@ fn main() {
 }
 
 fn run(cli: &Cli, output: &Output) -> Result<()> {
+    match &cli.command {
+        Some(Command::Check { target, dat, scan }) => {
+            return run_check(target, dat, scan, output);
+        }
+        Some(Command::Doctor {
+            dir,
+            destination,
+            json,
+            children,
+            scan,
+            output: output_args,
+        }) => {
+            let config = WriteConfig::from_args(output_args)?;
+            let m3u_dir = match children {
+                // Compare like paths, as a --children run sees DIR through TARGET
+                Some(target) => playlist_dir(
+                    &std::path::absolute(dir)?,
+                    destination.as_deref(),
+                    Some(&std::path::absolute(target)?),
+                ),
+                None => playlist_dir(dir, destination.as_deref(), None),
+            };
+            let report = doctor::diagnose(dir, scan, &m3u_dir, &config)?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print!("{}", report);
+            }
+            return Ok(());
+        }
+        None => {}
+    }
+
+    let config = WriteConfig::from_args(&cli.output)?;
+    if cli.print_config {
+        print!("{}", config);
+        return Ok(());
+    }
+
+    let dat = match &cli.dat {
+        Some(path) if cli.verify_completeness => Some(Dat::load(path)?),
+        _ => None,
+    };
+
     if cli.children {
-        run_children_mode(cli, output)
+        run_children_mode(cli, &config, dat.as_ref(), output)
     } else {
-        run_normal_mode(cli, output)
+        run_normal_mode(cli, &config, dat.as_ref(), output)
     }
 }
 
-fn run_normal_mode(cli: &Cli, output: &Output) -> Result<()> {
-    output.info(&format!("Scanning {}...", cli.target.display()));
+fn run_normal_mode(
+    cli: &Cli,
+    config: &WriteConfig,
+    dat: Option<&Dat>,
+    output: &Output,
+) -> Result<()> {
+    output.info(&format!("Scanning {}...", cli.target().display()));
 
     // Collect directories to process
-    let dirs: Vec<_> = WalkDir::new(&cli.target)
+    let dirs: Vec<_> = WalkDir::new(cli.target())
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_dir())
@@ -53,20 +115,24 @@ fn run_normal_mode(cli: &Cli, output: &Output) -> Result<()> {
     output.info(&format!("  Found {} directories to scan", dirs.len()));
 
     // Determine m3u destination
-    let m3u_base = cli.destination.as_ref().unwrap_or(&cli.target);
+    let m3u_base = cli.destination.as_deref().unwrap_or(cli.target());
 
     if let Some(dest) = &cli.destination {
         fs::create_dir_all(dest).context("Failed to create destination directory")?;
         check_and_clean_m3us(dest, output)?;
     }
+    clean_indexes(m3u_base, output)?;
 
     let pb = output.progress_bar(dirs.len() as u64);
     let mut total_m3us = 0;
+    let mut index = Vec::new();
+    // Groups to check against the DAT once all discs of a game have been seen
+    let mut checked = Vec::new();
 
     for dir in &dirs {
         pb.inc(1);
 
-        let files = match scan_directory(dir) {
+        let files = match scan_directory(dir, &cli.scan.exclude_ext) {
             Ok(f) => f,
             Err(e) => {
                 output.warning(&format!("Could not scan {}: {}", dir.display(), e));
@@ -80,44 +146,70 @@ fn run_normal_mode(cli: &Cli, output: &Output) -> Result<()> {
 
         output.verbose(&format!("Found {} media files in {}", files.len(), dir.display()));
 
-        let m3u_dir = if cli.destination.is_some() {
-            m3u_base.to_path_buf()
-        } else {
+        let m3u_dir = playlist_dir(dir, cli.destination.as_deref(), None);
+        if cli.destination.is_none() {
             // Check and clean m3us in the source directory
             if let Err(e) = check_and_clean_m3us(dir, output) {
                 output.warning(&format!("Skipping {}: {}", dir.display(), e));
                 continue;
             }
-            dir.to_path_buf()
-        };
+        }
 
-        let groups = group_files(files, cli.force);
+        let treat_as = dir_treat_as(dir, cli.scan.grouping_mode(), output);
+        let groups = group_files(files, treat_as);
+        if dat.is_some() {
+            checked.extend(groups.iter().cloned());
+        }
 
         for group in groups {
             let m3u_path = m3u_dir.join(format!("{}.m3u", group.name));
-            let relative_to = if cli.relative { Some(m3u_dir.as_path()) } else { None };
+            let anchor = config.paths.anchor(&m3u_dir);
 
-            write_m3u(&m3u_path, &group.files, relative_to)
+            let unreachable = write_m3u(&m3u_path, &group.files, anchor, &config.style)
                 .with_context(|| format!("Failed to write {}", m3u_path.display()))?;
+            warn_unreachable(&unreachable, anchor, output);
 
             output.verbose(&format!("Created {}", m3u_path.display()));
             total_m3us += 1;
+            index.push(IndexEntry {
+                name: group.name,
+                system: None,
+                discs: group.files.len(),
+                path: m3u_path,
+            });
         }
     }
 
     pb.finish_and_clear();
+
+    if let Some(dat) = dat {
+        warn_incomplete(&checked, dat, output);
+    }
+
+    if let Some(format) = cli.index {
+        let index_path = write_index(m3u_base, format, &index, &config.style)
+            .context("Failed to write index")?;
+        output.verbose(&format!("Created {}", index_path.display()));
+    }
+
     output.success(&format!("Done: Created {} m3u files", total_m3us));
 
     Ok(())
 }
 
-fn run_children_mode(cli: &Cli, output: &Output) -> Result<()> {
+fn run_children_mode(
+    cli: &Cli,
+    config: &WriteConfig,
+    dat: Option<&Dat>,
+    output: &Output,
+) -> Result<()> {
     let dest = cli.destination.as_ref().expect("validated in cli");
 
-    output.info(&format!("Scanning children of {}...", cli.target.display()));
+    output.info(&format!("Scanning children of {}...", cli.target().display()));
+    clean_indexes(dest, output)?;
 
     // Get top-level directories in target
-    let children: Vec<_> = fs::read_dir(&cli.target)?
+    let children: Vec<_> = fs::read_dir(cli.target())?
         .filter_map(|e| e.ok())
         .filter(|e| e.path().is_dir())
         .filter(|e| e.path() != *dest)
@@ -131,13 +223,14 @@ fn run_children_mode(cli: &Cli, output: &Output) -> Result<()> {
     output.info(&format!("  Found {} top-level directories", children.len()));
     let pb = output.progress_bar(children.len() as u64);
     let mut total_m3us = 0;
+    let mut index = Vec::new();
 
     for child in children {
         pb.inc(1);
         let child_path = child.path();
         let child_name = child.file_name();
 
-        let m3u_dir = dest.join(&child_name);
+        let m3u_dir = playlist_dir(&child_path, Some(dest), Some(cli.target()));
         fs::create_dir_all(&m3u_dir)?;
         check_and_clean_m3us(&m3u_dir, output)?;
 
@@ -148,9 +241,11 @@ fn run_children_mode(cli: &Cli, output: &Output) -> Result<()> {
             .filter(|e| e.file_type().is_dir())
             .map(|e| e.into_path())
             .collect();
+        // Groups to check against the DAT once the whole child has been seen
+        let mut checked = Vec::new();
 
         for dir in dirs {
-            let files = match scan_directory(&dir) {
+            let files = match scan_directory(&dir, &cli.scan.exclude_ext) {
                 Ok(f) => f,
                 Err(e) => {
                     output.warning(&format!("Could not scan {}: {}", dir.display(), e));
@@ -162,18 +257,33 @@ fn run_children_mode(cli: &Cli, output: &Output) -> Result<()> {
                 continue;
             }
 
-            let groups = group_files(files, cli.force);
+            let treat_as = dir_treat_as(&dir, cli.scan.grouping_mode(), output);
+            let groups = group_files(files, treat_as);
+            if dat.is_some() {
+                checked.extend(groups.iter().cloned());
+            }
 
             for group in groups {
                 let m3u_path = m3u_dir.join(format!("{}.m3u", group.name));
-                let relative_to = if cli.relative { Some(m3u_dir.as_path()) } else { None };
+                let anchor = config.paths.anchor(&m3u_dir);
 
-                write_m3u(&m3u_path, &group.files, relative_to)?;
+                let unreachable = write_m3u(&m3u_path, &group.files, anchor, &config.style)?;
+                warn_unreachable(&unreachable, anchor, output);
                 output.verbose(&format!("Created {}", m3u_path.display()));
                 total_m3us += 1;
+                index.push(IndexEntry {
+                    name: group.name,
+                    system: Some(child_name.to_string_lossy().to_string()),
+                    discs: group.files.len(),
+                    path: m3u_path,
+                });
             }
         }
 
+        if let Some(dat) = dat {
+            warn_incomplete(&checked, dat, output);
+        }
+
         // Remove empty directories
         if fs::read_dir(&m3u_dir)?.next().is_none() {
             fs::remove_dir(&m3u_dir)?;
@@ -181,11 +291,102 @@ fn run_children_mode(cli: &Cli, output: &Output) -> Result<()> {
     }
 
     pb.finish_and_clear();
+
+    if let Some(format) = cli.index {
+        let index_path = write_index(dest, format, &index, &config.style)
+            .context("Failed to write index")?;
+        output.verbose(&format!("Created {}", index_path.display()));
+    }
+
     output.success(&format!("Done: Created {} m3u files", total_m3us));
 
     Ok(())
 }
 
+fn run_check(target: &Path, dat_path: &Path, scan: &ScanArgs, output: &Output) -> Result<()> {
+    let dat = Dat::load(dat_path)?;
+    output.info(&format!(
+        "Checking {} against {} games in {}...",
+        target.display(),
+        dat.games.len(),
+        dat_path.display()
+    ));
+
+    // A game's discs may be spread over several directories, so every group
+    // is collected before comparing
+    let mut groups = Vec::new();
+
+    for entry in WalkDir::new(target).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        let files = match scan_directory(entry.path(), &scan.exclude_ext) {
+            Ok(f) => f,
+            Err(e) => {
+                output.warning(&format!("Could not scan {}: {}", entry.path().display(), e));
+                continue;
+            }
+        };
+
+        let treat_as = dir_treat_as(entry.path(), scan.grouping_mode(), output);
+
+        groups.extend(group_files(files, treat_as));
+    }
+
+    let incomplete = check_completeness(&dat, &groups);
+    for missing in &incomplete {
+        println!("{}", missing);
+    }
+
+    output.success(&format!("Done: {} incomplete games", incomplete.len()));
+
+    Ok(())
+}
+
+/// The grouping mode for one directory, honouring its override file
+fn dir_treat_as(dir: &Path, default: TreatAs, output: &Output) -> TreatAs {
+    match DirOverrides::load(dir) {
+        Ok(overrides) => overrides.treat_as.unwrap_or(default),
+        Err(e) => {
+            output.warning(&format!(
+                "Ignoring {} in {}: {:#}",
+                OVERRIDE_FILENAME,
+                dir.display(),
+                e
+            ));
+            default
+        }
+    }
+}
+
+fn warn_incomplete(groups: &[GameSet], dat: &Dat, output: &Output) {
+    for missing in check_completeness(dat, groups) {
+        output.warning(&missing.to_string());
+    }
+}
+
+fn warn_unreachable(unreachable: &[PathBuf], anchor: Anchor, output: &Output) {
+    let Some(base) = anchor.base() else { return };
+    for path in unreachable {
+        output.warning(&format!(
+            "{} cannot be expressed relative to {}, writing it unchanged",
+            path.display(),
+            base.display()
+        ));
+    }
+}
+
+/// Remove the indexes of a previous run, whether or not this one writes one
+fn clean_indexes(dir: &Path, output: &Output) -> Result<()> {
+    let removed = remove_indexes(dir)
+        .with_context(|| format!("Failed to remove old index in {}", dir.display()))?;
+    for path in removed {
+        output.verbose(&format!("Removed old {}", path.display()));
+    }
+    Ok(())
+}
+
 fn check_and_clean_m3us(dir: &Path, output: &Output) -> Result<()> {
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
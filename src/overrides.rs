@@ -0,0 +1,98 @@
+// Overrides module for per-directory settings files
+
+use crate::types::TreatAs;
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use std::fs;
+use std::path::Path;
+
+/// Name of the optional settings file inside a ROM directory
+pub const OVERRIDE_FILENAME: &str = ".m3u-emu";
+
+/// Settings that replace the command line options for one directory
+///
+/// The file holds `key = value` lines; blank lines and lines starting
+/// with `#` are ignored. Supported keys:
+///
+/// - `treat-as`: `auto`, `disc` or `floppy`, as for `--treat-as`
+#[derive(Debug, Default, PartialEq)]
+pub struct DirOverrides {
+    pub treat_as: Option<TreatAs>,
+}
+
+impl DirOverrides {
+    /// Load the override file from a directory, if it has one
+    pub fn load(dir: &Path) -> Result<DirOverrides> {
+        let path = dir.join(OVERRIDE_FILENAME);
+        if !path.is_file() {
+            return Ok(DirOverrides::default());
+        }
+        DirOverrides::parse(&fs::read_to_string(&path)?)
+    }
+
+    /// Parse the contents of an override file
+    pub fn parse(content: &str) -> Result<DirOverrides> {
+        let mut overrides = DirOverrides::default();
+
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                bail!("expected `key = value`, found `{}`", line);
+            };
+
+            match key.trim() {
+                "treat-as" => {
+                    let value = value.trim();
+                    overrides.treat_as = Some(
+                        TreatAs::from_str(value, true)
+                            .map_err(|_| anyhow::anyhow!("invalid treat-as value `{}`", value))?,
+                    );
+                }
+                other => bail!("unknown setting `{}`", other),
+            }
+        }
+
+        Ok(overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_treat_as() {
+        let overrides = DirOverrides::parse("# demo parts\ntreat-as = Floppy\n").unwrap();
+        assert_eq!(overrides.treat_as, Some(TreatAs::Floppy));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert_eq!(DirOverrides::parse("\n# nothing\n").unwrap(), DirOverrides::default());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(DirOverrides::parse("treat-as = cassette").is_err());
+        assert!(DirOverrides::parse("relative = yes").is_err());
+        assert!(DirOverrides::parse("treat-as").is_err());
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(DirOverrides::load(dir.path()).unwrap(), DirOverrides::default());
+    }
+
+    #[test]
+    fn test_load_file() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(OVERRIDE_FILENAME), "treat-as=disc\n").unwrap();
+        let overrides = DirOverrides::load(dir.path()).unwrap();
+        assert_eq!(overrides.treat_as, Some(TreatAs::Disc));
+    }
+}
@@ -4,7 +4,8 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use std::collections::HashMap;
 
-/// Convert English word numerals and single letters to numbers
+/// Convert word numerals (English, French, German, Spanish, Italian) and
+/// single letters to numbers
 pub fn word_to_number(word: &str) -> Option<u32> {
     static NUMERALS: Lazy<HashMap<&'static str, u32>> = Lazy::new(|| {
         let mut m = HashMap::new();
@@ -45,6 +46,56 @@ pub fn word_to_number(word: &str) -> Option<u32> {
         m
     });
 
+    // French, German, Spanish and Italian numerals 1-12. Looked up only after
+    // the English table, so English wins on any conflict.
+    static LOCALIZED_NUMERALS: Lazy<HashMap<&'static str, u32>> = Lazy::new(|| {
+        let mut m = HashMap::new();
+        // French
+        for (i, w) in [
+            "un", "deux", "trois", "quatre", "cinq", "six",
+            "sept", "huit", "neuf", "dix", "onze", "douze",
+        ]
+        .iter()
+        .enumerate()
+        {
+            m.entry(*w).or_insert(i as u32 + 1);
+        }
+        m.insert("une", 1);
+        // German
+        for (i, w) in [
+            "eins", "zwei", "drei", "vier", "fünf", "sechs",
+            "sieben", "acht", "neun", "zehn", "elf", "zwölf",
+        ]
+        .iter()
+        .enumerate()
+        {
+            m.entry(*w).or_insert(i as u32 + 1);
+        }
+        m.insert("fuenf", 5);
+        m.insert("zwoelf", 12);
+        // Spanish
+        for (i, w) in [
+            "uno", "dos", "tres", "cuatro", "cinco", "seis",
+            "siete", "ocho", "nueve", "diez", "once", "doce",
+        ]
+        .iter()
+        .enumerate()
+        {
+            m.entry(*w).or_insert(i as u32 + 1);
+        }
+        // Italian
+        for (i, w) in [
+            "uno", "due", "tre", "quattro", "cinque", "sei",
+            "sette", "otto", "nove", "dieci", "undici", "dodici",
+        ]
+        .iter()
+        .enumerate()
+        {
+            m.entry(*w).or_insert(i as u32 + 1);
+        }
+        m
+    });
+
     let lower = word.to_lowercase();
 
     // Check numerals (case-insensitive)
@@ -52,6 +103,11 @@ pub fn word_to_number(word: &str) -> Option<u32> {
         return Some(n);
     }
 
+    // Then localized numerals (case-insensitive)
+    if let Some(&n) = LOCALIZED_NUMERALS.get(lower.as_str()) {
+        return Some(n);
+    }
+
     // Check single uppercase letters A-Z (case-sensitive!)
     if word.len() == 1 {
         let ch = word.chars().next().unwrap();
@@ -66,7 +122,7 @@ pub fn word_to_number(word: &str) -> Option<u32> {
 static NUMBER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
 
 static ORDER_REGEX: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)\(\s*(floppy\s|diskette\s|disk\s|cd\s|disc\s|boot|save)[^)(]*\)").unwrap()
+    Regex::new(r"(?i)\(\s*(floppy\s|diskette\s|disquette\s|disk\s|disque\s|disco\s|cd\s|disc\s|boot|save)[^)(]*\)").unwrap()
 });
 
 static SIDE_REGEX: Lazy<Regex> = Lazy::new(|| {
@@ -78,6 +134,10 @@ static SIDE_REGEX: Lazy<Regex> = Lazy::new(|| {
 pub struct ParsedFilename {
     pub base_name: String,
     pub disc_number: f32,
+    /// The disc/cd/floppy marker that matched, e.g. "(Disc 2)"
+    pub disc_marker: Option<String>,
+    /// The side marker that matched, e.g. "(Side A)"
+    pub side_marker: Option<String>,
 }
 
 /// Parse a filename to extract base name and disc number
@@ -88,12 +148,21 @@ pub fn parse_filename(filename: &str) -> ParsedFilename {
         None => filename,
     };
 
+    parse_name(name)
+}
+
+/// Parse a name without extension (e.g. a DAT game name) to extract base
+/// name and disc number
+pub fn parse_name(name: &str) -> ParsedFilename {
     let mut base_name = name.to_string();
     let mut disc_number: f32 = 1.0;
+    let mut disc_marker = None;
+    let mut side_marker = None;
 
     // Extract disc/cd/floppy identifier
     if let Some(m) = ORDER_REGEX.find(name) {
         let matched = m.as_str();
+        disc_marker = Some(matched.to_string());
         // Extract just the content inside parentheses
         let inner = &matched[1..matched.len() - 1];
         if let Some(n) = extract_number(inner) {
@@ -106,6 +175,7 @@ pub fn parse_filename(filename: &str) -> ParsedFilename {
     // Extract side identifier
     if let Some(m) = SIDE_REGEX.find(name) {
         let matched = m.as_str();
+        side_marker = Some(matched.to_string());
         let inner = &matched[1..matched.len() - 1];
         if let Some(n) = extract_number(inner) {
             disc_number += n as f32 * 0.1;
@@ -122,6 +192,8 @@ pub fn parse_filename(filename: &str) -> ParsedFilename {
     ParsedFilename {
         base_name,
         disc_number,
+        disc_marker,
+        side_marker,
     }
 }
 
@@ -201,6 +273,40 @@ mod tests {
         assert_eq!(word_to_number(""), None);
     }
 
+    #[test]
+    fn test_word_to_number_french() {
+        assert_eq!(word_to_number("un"), Some(1));
+        assert_eq!(word_to_number("Deux"), Some(2));
+        assert_eq!(word_to_number("NEUF"), Some(9));
+        assert_eq!(word_to_number("douze"), Some(12));
+    }
+
+    #[test]
+    fn test_word_to_number_german() {
+        assert_eq!(word_to_number("Zwei"), Some(2));
+        assert_eq!(word_to_number("fünf"), Some(5));
+        assert_eq!(word_to_number("FÜNF"), Some(5));
+        assert_eq!(word_to_number("fuenf"), Some(5));
+        assert_eq!(word_to_number("elf"), Some(11));
+        assert_eq!(word_to_number("Zwölf"), Some(12));
+    }
+
+    #[test]
+    fn test_word_to_number_spanish() {
+        assert_eq!(word_to_number("Uno"), Some(1));
+        assert_eq!(word_to_number("dos"), Some(2));
+        assert_eq!(word_to_number("CUATRO"), Some(4));
+        assert_eq!(word_to_number("once"), Some(11));
+    }
+
+    #[test]
+    fn test_word_to_number_italian() {
+        assert_eq!(word_to_number("Due"), Some(2));
+        assert_eq!(word_to_number("tre"), Some(3));
+        assert_eq!(word_to_number("sei"), Some(6));
+        assert_eq!(word_to_number("dodici"), Some(12));
+    }
+
     #[test]
     fn test_extract_number_digits() {
         assert_eq!(extract_number("2"), Some(2));
@@ -271,6 +377,15 @@ mod tests {
         let result = parse_filename("Single Game.iso");
         assert_eq!(result.base_name, "Single Game");
         assert_eq!(result.disc_number, 1.0);
+        assert!(result.disc_marker.is_none());
+        assert!(result.side_marker.is_none());
+    }
+
+    #[test]
+    fn test_parse_filename_markers() {
+        let result = parse_filename("Game (USA) (Disk 2) (Side B).adf");
+        assert_eq!(result.disc_marker.as_deref(), Some("(Disk 2)"));
+        assert_eq!(result.side_marker.as_deref(), Some("(Side B)"));
     }
 
     #[test]
@@ -280,6 +395,38 @@ mod tests {
         assert_eq!(result.disc_number, 2.0);
     }
 
+    #[test]
+    fn test_parse_filename_french_number() {
+        let result = parse_filename("Jeu (Disque Deux).cue");
+        assert_eq!(result.base_name, "Jeu");
+        assert_eq!(result.disc_number, 2.0);
+
+        let result = parse_filename("Jeu (Disquette Trois).adf");
+        assert_eq!(result.base_name, "Jeu");
+        assert_eq!(result.disc_number, 3.0);
+    }
+
+    #[test]
+    fn test_parse_filename_german_number() {
+        let result = parse_filename("Spiel (Diskette Zwei).adf");
+        assert_eq!(result.base_name, "Spiel");
+        assert_eq!(result.disc_number, 2.0);
+    }
+
+    #[test]
+    fn test_parse_filename_spanish_number() {
+        let result = parse_filename("Juego (CD Tres).iso");
+        assert_eq!(result.base_name, "Juego");
+        assert_eq!(result.disc_number, 3.0);
+    }
+
+    #[test]
+    fn test_parse_filename_italian_number() {
+        let result = parse_filename("Gioco (Disco Due).cue");
+        assert_eq!(result.base_name, "Gioco");
+        assert_eq!(result.disc_number, 2.0);
+    }
+
     #[test]
     fn test_parse_filename_boot_save() {
         let boot = parse_filename("Game (Boot).adf");
@@ -295,4 +442,11 @@ mod tests {
         assert_eq!(result.base_name, "Game (USA)");
         assert_eq!(result.disc_number, 1.0);
     }
+
+    #[test]
+    fn test_parse_name_keeps_dots() {
+        let result = parse_name("Riven - Vol. 2 (USA) (Disc 3)");
+        assert_eq!(result.base_name, "Riven - Vol. 2 (USA)");
+        assert_eq!(result.disc_number, 3.0);
+    }
 }
@@ -6,11 +6,51 @@ use anyhow::Result;
 use std::fs;
 use std::path::Path;
 
-/// Scan a single directory for media files
-pub fn scan_directory(dir: &Path) -> Result<Vec<MediaFile>> {
-    let mut floppy_files = Vec::new();
-    let mut disc_index_files = Vec::new();
-    let mut disc_image_files = Vec::new();
+/// Media files found in a directory, by type, before priority selection
+#[derive(Debug, Default)]
+pub struct DirectoryScan {
+    pub floppy: Vec<MediaFile>,
+    pub disc_index: Vec<MediaFile>,
+    pub disc_image: Vec<MediaFile>,
+}
+
+impl DirectoryScan {
+    /// The media type that wins the priority: floppy > disc index > disc image
+    pub fn selected_type(&self) -> Option<MediaType> {
+        // If we have floppy files, use those
+        // If we have disc index files, use those (not images)
+        // Otherwise use disc images
+        if !self.floppy.is_empty() {
+            Some(MediaType::Floppy)
+        } else if !self.disc_index.is_empty() {
+            Some(MediaType::DiscIndex)
+        } else if !self.disc_image.is_empty() {
+            Some(MediaType::DiscImage)
+        } else {
+            None
+        }
+    }
+
+    /// The files of the selected media type
+    pub fn into_selected(self) -> Vec<MediaFile> {
+        match self.selected_type() {
+            Some(MediaType::Floppy) => self.floppy,
+            Some(MediaType::DiscIndex) => self.disc_index,
+            Some(MediaType::DiscImage) => self.disc_image,
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Scan a single directory for media files, ignoring the (lowercase)
+/// extensions in `exclude`
+pub fn scan_directory(dir: &Path, exclude: &[String]) -> Result<Vec<MediaFile>> {
+    Ok(classify_directory(dir, exclude)?.into_selected())
+}
+
+/// Scan a single directory and sort its media files by type
+pub fn classify_directory(dir: &Path, exclude: &[String]) -> Result<DirectoryScan> {
+    let mut scan = DirectoryScan::default();
 
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
@@ -30,6 +70,10 @@ pub fn scan_directory(dir: &Path) -> Result<Vec<MediaFile>> {
             None => continue,
         };
 
+        if exclude.iter().any(|x| x.eq_ignore_ascii_case(ext)) {
+            continue;
+        }
+
         let media_type = match MediaType::from_extension(ext) {
             Some(t) => t,
             None => continue,
@@ -45,23 +89,13 @@ pub fn scan_directory(dir: &Path) -> Result<Vec<MediaFile>> {
         };
 
         match media_type {
-            MediaType::Floppy => floppy_files.push(media_file),
-            MediaType::DiscIndex => disc_index_files.push(media_file),
-            MediaType::DiscImage => disc_image_files.push(media_file),
+            MediaType::Floppy => scan.floppy.push(media_file),
+            MediaType::DiscIndex => scan.disc_index.push(media_file),
+            MediaType::DiscImage => scan.disc_image.push(media_file),
         }
     }
 
-    // Priority: floppy > disc index > disc image
-    // If we have floppy files, return those
-    // If we have disc index files, return those (not images)
-    // Otherwise return disc images
-    if !floppy_files.is_empty() {
-        Ok(floppy_files)
-    } else if !disc_index_files.is_empty() {
-        Ok(disc_index_files)
-    } else {
-        Ok(disc_image_files)
-    }
+    Ok(scan)
 }
 
 #[cfg(test)]
@@ -81,7 +115,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         create_test_files(dir.path(), &["game.cue", "game.bin", "readme.txt"]);
 
-        let files = scan_directory(dir.path()).unwrap();
+        let files = scan_directory(dir.path(), &[]).unwrap();
         assert_eq!(files.len(), 1);
         assert!(files[0].filename.ends_with(".cue"));
     }
@@ -91,7 +125,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         create_test_files(dir.path(), &["game.adf", "game2.d64"]);
 
-        let files = scan_directory(dir.path()).unwrap();
+        let files = scan_directory(dir.path(), &[]).unwrap();
         assert_eq!(files.len(), 2);
     }
 
@@ -100,7 +134,7 @@ mod tests {
         let dir = TempDir::new().unwrap();
         create_test_files(dir.path(), &["game.cue", "game.iso", "game.bin"]);
 
-        let files = scan_directory(dir.path()).unwrap();
+        let files = scan_directory(dir.path(), &[]).unwrap();
         // Should only return .cue since it's an index format
         assert_eq!(files.len(), 1);
         assert!(files[0].filename.ends_with(".cue"));
@@ -109,7 +143,7 @@ mod tests {
     #[test]
     fn test_scan_directory_empty() {
         let dir = TempDir::new().unwrap();
-        let files = scan_directory(dir.path()).unwrap();
+        let files = scan_directory(dir.path(), &[]).unwrap();
         assert!(files.is_empty());
     }
 
@@ -118,7 +152,30 @@ mod tests {
         let dir = TempDir::new().unwrap();
         create_test_files(dir.path(), &["game.CUE", "game.ISO"]);
 
-        let files = scan_directory(dir.path()).unwrap();
+        let files = scan_directory(dir.path(), &[]).unwrap();
         assert_eq!(files.len(), 1); // .CUE preferred over .ISO
     }
+
+    #[test]
+    fn test_classify_directory() {
+        let dir = TempDir::new().unwrap();
+        create_test_files(dir.path(), &["game.cue", "game.iso", "game.chd", "readme.txt"]);
+
+        let scan = classify_directory(dir.path(), &[]).unwrap();
+        assert!(scan.floppy.is_empty());
+        assert_eq!(scan.disc_index.len(), 1);
+        assert_eq!(scan.disc_image.len(), 2);
+        assert_eq!(scan.selected_type(), Some(MediaType::DiscIndex));
+    }
+
+    #[test]
+    fn test_scan_directory_exclude_ext() {
+        let dir = TempDir::new().unwrap();
+        create_test_files(dir.path(), &["game.cue", "game.bin", "game.ISO"]);
+
+        // With .cue excluded, the image is selected as if no index existed
+        let files = scan_directory(dir.path(), &["cue".to_string()]).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].filename.ends_with(".ISO"));
+    }
 }
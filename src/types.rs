@@ -1,7 +1,9 @@
+use serde::Serialize;
 use std::path::PathBuf;
 
 /// Type of media file detected from extension
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum MediaType {
     /// Floppy disk formats (adf, d64, etc.)
     Floppy,
@@ -35,19 +37,40 @@ impl MediaType {
         }
     }
 
+    /// Human-readable name of the media type
+    pub fn description(&self) -> &'static str {
+        match self {
+            MediaType::Floppy => "floppy",
+            MediaType::DiscIndex => "disc index",
+            MediaType::DiscImage => "disc image",
+        }
+    }
+
     /// Check if this is a floppy format
     pub fn is_floppy(&self) -> bool {
         matches!(self, MediaType::Floppy)
     }
 }
 
+/// How a directory's files are grouped into playlists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TreatAs {
+    /// Floppy style if any floppy format is present, disc style otherwise
+    #[default]
+    Auto,
+    /// Group by base name, one playlist per game
+    Disc,
+    /// Everything in the directory goes into one playlist
+    Floppy,
+}
+
 /// A media file with parsed metadata
 #[derive(Debug, Clone)]
 pub struct MediaFile {
     /// Full path to the file
     pub path: PathBuf,
     /// Just the filename (no directory)
-    #[allow(dead_code)] // Used in tests and for debugging
     pub filename: String,
     /// Name with disc/side markers removed (for grouping)
     pub base_name: String,
@@ -90,6 +90,216 @@ fn test_relative_paths() {
     }
 }
 
+#[test]
+fn test_relative_to_base() {
+    let dir = TempDir::new().unwrap();
+    let dest = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--relative-to")
+        .arg(dir.path())
+        .arg(dir.path().join("psx"))
+        .arg(dest.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let content = fs::read_to_string(dest.path().join("Final Fantasy VII.m3u")).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 3);
+
+    // Entries are anchored at the chosen base, not the m3u's directory
+    let expected = std::path::Path::new("psx")
+        .join("Final Fantasy VII")
+        .join("Final Fantasy VII (Disc 1).cue");
+    assert_eq!(lines[0], expected.to_string_lossy());
+}
+
+#[test]
+fn test_relative_to_base_with_parent_dir() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let playlists = dir.path().join("playlists");
+    fs::create_dir(&playlists).unwrap();
+
+    // Run from the playlist directory with the base given as ../
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .current_dir(&playlists)
+        .arg("--relative-to")
+        .arg("..")
+        .arg(dir.path().join("psx"))
+        .arg(".")
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("cannot be expressed"), "stderr: {}", stderr);
+
+    let content = fs::read_to_string(playlists.join("Final Fantasy VII.m3u")).unwrap();
+    let expected = std::path::Path::new("psx")
+        .join("Final Fantasy VII")
+        .join("Final Fantasy VII (Disc 1).cue");
+    assert_eq!(content.lines().next().unwrap(), expected.to_string_lossy());
+}
+
+#[test]
+fn test_relative_to_base_outside_roms() {
+    let dir = TempDir::new().unwrap();
+    let dest = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let base = dir.path().join("other");
+    fs::create_dir(&base).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--relative-to")
+        .arg(&base)
+        .arg(dir.path().join("psx"))
+        .arg(dest.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // Entries outside the base are written unchanged, with a warning
+    let content = fs::read_to_string(dest.path().join("Final Fantasy VII.m3u")).unwrap();
+    let expected = dir
+        .path()
+        .join("psx/Final Fantasy VII/Final Fantasy VII (Disc 1).cue");
+    assert_eq!(content.lines().next().unwrap(), expected.to_string_lossy());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("cannot be expressed relative to"), "stderr: {}", stderr);
+}
+
+#[test]
+fn test_exclude_ext() {
+    let dir = TempDir::new().unwrap();
+    let game = dir.path().join("Riven");
+    fs::create_dir_all(&game).unwrap();
+    for disc in 1..=2 {
+        File::create(game.join(format!("Riven (Disc {}).iso", disc))).unwrap();
+        File::create(game.join(format!("Riven (Disc {}).chd", disc))).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--exclude-ext")
+        .arg("CHD")
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let content = fs::read_to_string(game.join("Riven.m3u")).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|l| l.ends_with(".iso")));
+}
+
+fn create_treat_as_structure(dir: &std::path::Path) {
+    // Loose disc images that belong to one demo
+    let demo = dir.join("demos").join("Second Reality");
+    fs::create_dir_all(&demo).unwrap();
+    File::create(demo.join("Intro.iso")).unwrap();
+    File::create(demo.join("Main Part.iso")).unwrap();
+    File::create(demo.join("Outro.iso")).unwrap();
+
+    // Floppies of two different games in one directory
+    let floppies = dir.join("floppies").join("Mixed");
+    fs::create_dir_all(&floppies).unwrap();
+    File::create(floppies.join("Game (Disk 1).adf")).unwrap();
+    File::create(floppies.join("Game (Disk 2).adf")).unwrap();
+    File::create(floppies.join("Other.adf")).unwrap();
+}
+
+fn count_m3us(dir: &std::path::Path) -> usize {
+    fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().map(|x| x == "m3u").unwrap_or(false))
+        .count()
+}
+
+#[test]
+fn test_treat_as_floppy() {
+    let dir = TempDir::new().unwrap();
+    create_treat_as_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--treat-as")
+        .arg("floppy")
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // Disc images forced into a single playlist, named after the first file
+    // and ordered by filename since none has a disc marker
+    let demo = dir.path().join("demos/Second Reality");
+    assert_eq!(count_m3us(&demo), 1);
+    let content = fs::read_to_string(demo.join("Intro.m3u")).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(
+        lines,
+        vec![
+            demo.join("Intro.iso").to_string_lossy(),
+            demo.join("Main Part.iso").to_string_lossy(),
+            demo.join("Outro.iso").to_string_lossy(),
+        ]
+    );
+
+    // Floppies keep their floppy grouping
+    assert_eq!(count_m3us(&dir.path().join("floppies/Mixed")), 1);
+}
+
+#[test]
+fn test_treat_as_disc() {
+    let dir = TempDir::new().unwrap();
+    create_treat_as_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--treat-as")
+        .arg("disc")
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // Floppies grouped by name like discs
+    let floppies = dir.path().join("floppies/Mixed");
+    assert_eq!(count_m3us(&floppies), 2);
+    let content = fs::read_to_string(floppies.join("Game.m3u")).unwrap();
+    assert_eq!(content.lines().count(), 2);
+
+    // Disc images keep their disc grouping
+    assert_eq!(count_m3us(&dir.path().join("demos/Second Reality")), 3);
+}
+
+#[test]
+fn test_treat_as_override_file() {
+    let dir = TempDir::new().unwrap();
+    create_treat_as_structure(dir.path());
+    fs::write(dir.path().join("demos/Second Reality/.m3u-emu"), "treat-as = floppy\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--treat-as")
+        .arg("disc")
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // The override file wins over the command line for its directory only
+    assert_eq!(count_m3us(&dir.path().join("demos/Second Reality")), 1);
+    assert_eq!(count_m3us(&dir.path().join("floppies/Mixed")), 2);
+}
+
 #[test]
 fn test_children_mode() {
     let dir = TempDir::new().unwrap();
@@ -129,3 +339,432 @@ fn test_quiet_mode() {
     // Quiet mode should have no stdout (progress goes to stderr)
     assert!(output.stdout.is_empty());
 }
+
+fn write_psx_dat(path: &std::path::Path) {
+    let mut xml = String::from("<?xml version=\"1.0\"?>\n<datafile>\n");
+    for disc in 1..=4 {
+        xml.push_str(&format!(
+            "\t<game name=\"Final Fantasy VII (Disc {0})\">\n\t\t<rom name=\"Final Fantasy VII (Disc {0}).cue\" size=\"100\" crc=\"00000000\"/>\n\t</game>\n",
+            disc
+        ));
+    }
+    xml.push_str("</datafile>\n");
+    fs::write(path, xml).unwrap();
+}
+
+#[test]
+fn test_verify_completeness() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let dat = dir.path().join("psx.dat");
+    write_psx_dat(&dat);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--dat")
+        .arg(&dat)
+        .arg("--verify-completeness")
+        .arg(dir.path().join("psx"))
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // The playlist is still written, with a warning about the missing disc
+    assert!(dir.path().join("psx/Final Fantasy VII/Final Fantasy VII.m3u").exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("found 3 of 4 discs"), "stderr: {}", stderr);
+    assert!(stderr.contains("Final Fantasy VII (Disc 4)"));
+}
+
+#[test]
+fn test_check_subcommand() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let dat = dir.path().join("psx.dat");
+    write_psx_dat(&dat);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("check")
+        .arg("--dat")
+        .arg(&dat)
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // Only the game known to the DAT is reported, and nothing is written
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1, "stdout: {}", stdout);
+    assert!(lines[0].starts_with("Final Fantasy VII: found 3 of 4 discs"));
+    assert!(!dir.path().join("psx/Final Fantasy VII/Final Fantasy VII.m3u").exists());
+}
+
+#[test]
+fn test_check_subcommand_merges_directories() {
+    let dir = TempDir::new().unwrap();
+    let dat = dir.path().join("psx.dat");
+    write_psx_dat(&dat);
+
+    // One game with its discs split over two directories
+    let first = dir.path().join("a");
+    let second = dir.path().join("b");
+    fs::create_dir_all(&first).unwrap();
+    fs::create_dir_all(&second).unwrap();
+    File::create(first.join("Final Fantasy VII (Disc 1).cue")).unwrap();
+    File::create(first.join("Final Fantasy VII (Disc 2).cue")).unwrap();
+    File::create(second.join("Final Fantasy VII (Disc 3).cue")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("check")
+        .arg("--dat")
+        .arg(&dat)
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["Final Fantasy VII: found 3 of 4 discs, missing Final Fantasy VII (Disc 4)"]
+    );
+}
+
+#[test]
+fn test_check_subcommand_scan_options() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let dat = dir.path().join("psx.dat");
+    write_psx_dat(&dat);
+
+    // With the cue files excluded, as in a run, there is nothing to report
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("check")
+        .arg("--dat")
+        .arg(&dat)
+        .arg("--exclude-ext")
+        .arg("cue")
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    assert!(output.stdout.is_empty(), "stdout: {}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn test_doctor() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let game = dir.path().join("psx/Final Fantasy VII");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("doctor")
+        .arg(&game)
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Final Fantasy VII (Disc 1).bin"));
+    assert!(stdout.contains("extension .bin is not a media format"));
+    assert!(stdout.contains("marker: disc (Disc 2)"));
+    assert!(stdout.contains("disc index selected"));
+    assert!(stdout.contains("Final Fantasy VII.m3u"));
+
+    // Doctor only explains, it never writes playlists
+    assert!(!game.join("Final Fantasy VII.m3u").exists());
+}
+
+#[test]
+fn test_doctor_json() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("doctor")
+        .arg("--json")
+        .arg(dir.path().join("amiga/Monkey Island"))
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.trim_start().starts_with('{'));
+    assert!(stdout.contains("\"media_type\": \"floppy\""));
+    assert!(stdout.contains("\"floppy_mode\": true"));
+    assert!(stdout.contains("\"disc_marker\": \"(Disk 4)\""));
+}
+
+#[test]
+fn test_doctor_matches_children_run() {
+    let dir = TempDir::new().unwrap();
+    let dest = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let game = dir.path().join("psx/Final Fantasy VII");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("doctor")
+        .arg("--json")
+        .arg("--children")
+        .arg(dir.path())
+        .arg("--relative")
+        .arg(&game)
+        .arg(dest.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let group = &report["groups"][0];
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--children")
+        .arg("--relative")
+        .arg(dir.path())
+        .arg(dest.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // The playlist doctor describes is exactly the one the run writes
+    let playlist = dest.path().join("psx/Final Fantasy VII.m3u");
+    assert_eq!(group["playlist"], playlist.to_string_lossy().as_ref());
+    let content = fs::read_to_string(&playlist).unwrap();
+    let entries: Vec<_> = group["entries"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e.as_str().unwrap())
+        .collect();
+    assert_eq!(content.lines().collect::<Vec<_>>(), entries);
+}
+
+#[test]
+fn test_format_retroarch() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--format")
+        .arg("retroarch")
+        .arg(dir.path().join("psx"))
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // Co-located entries are bare filenames with a disc label, one per
+    // LF-terminated line
+    let m3u_path = dir.path().join("psx/Final Fantasy VII/Final Fantasy VII.m3u");
+    let content = fs::read_to_string(&m3u_path).unwrap();
+    assert_eq!(
+        content,
+        "Final Fantasy VII (Disc 1).cue|Disc 1\n\
+         Final Fantasy VII (Disc 2).cue|Disc 2\n\
+         Final Fantasy VII (Disc 3).cue|Disc 3\n"
+    );
+}
+
+#[test]
+fn test_format_generic() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--format")
+        .arg("generic")
+        .arg(dir.path().join("psx"))
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let game = dir.path().join("psx/Final Fantasy VII");
+    let content = fs::read_to_string(game.join("Final Fantasy VII.m3u")).unwrap();
+    assert!(!content.contains('\r'));
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        game.join("Final Fantasy VII (Disc 1).cue").to_string_lossy()
+    );
+}
+
+#[test]
+fn test_format_flag_override() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--format")
+        .arg("retroarch")
+        .arg("--line-ending")
+        .arg("crlf")
+        .arg(dir.path().join("psx"))
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let m3u_path = dir.path().join("psx/Final Fantasy VII/Final Fantasy VII.m3u");
+    let content = fs::read_to_string(&m3u_path).unwrap();
+    assert!(content.starts_with("Final Fantasy VII (Disc 1).cue|Disc 1\r\n"));
+}
+
+#[test]
+fn test_print_config() {
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--format")
+        .arg("windows-frontend")
+        .arg("--print-config")
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("format = windows-frontend"));
+    assert!(stdout.contains("paths = absolute  (--format windows-frontend)"));
+    assert!(stdout.contains("path-separator = backslash"));
+    assert!(stdout.contains("line-ending = crlf"));
+    assert!(stdout.contains("labels = off  (default)"));
+}
+
+#[test]
+fn test_print_config_skips_dat() {
+    // The DAT is only needed to write playlists, so a missing one is fine
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--print-config")
+        .arg("--dat")
+        .arg("/nonexistent/redump.dat")
+        .arg("--verify-completeness")
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("format = generic"));
+}
+
+#[test]
+fn test_index_m3u() {
+    let dir = TempDir::new().unwrap();
+    let dest = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--children")
+        .arg("--index")
+        .arg(dir.path())
+        .arg(dest.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    let content = fs::read_to_string(dest.path().join("index.m3u")).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    let amiga = std::path::Path::new("amiga").join("Monkey Island.m3u");
+    let psx = std::path::Path::new("psx").join("Final Fantasy VII.m3u");
+    assert_eq!(
+        lines,
+        vec!["# m3u-emu index".into(), amiga.to_string_lossy(), psx.to_string_lossy()]
+    );
+}
+
+#[test]
+fn test_index_csv_regenerated() {
+    let dir = TempDir::new().unwrap();
+    let dest = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let run = || {
+        Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+            .arg("--children")
+            .arg("--index=csv")
+            .arg(dir.path())
+            .arg(dest.path())
+            .output()
+            .expect("Failed to run m3u-emu")
+    };
+
+    assert!(run().status.success());
+    fs::remove_dir_all(dir.path().join("amiga")).unwrap();
+    assert!(run().status.success());
+
+    // The second run replaces the index instead of appending to it
+    let content = fs::read_to_string(dest.path().join("index.csv")).unwrap();
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], "name,system,discs,path");
+    assert!(lines[1].starts_with("Final Fantasy VII,psx,3,"));
+}
+
+#[test]
+fn test_index_removed_when_dropped() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+
+    let run = |index: Option<&str>| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_m3u-emu"));
+        if let Some(index) = index {
+            cmd.arg(index);
+        }
+        cmd.arg(dir.path()).output().expect("Failed to run m3u-emu")
+    };
+
+    // Without a destination the index goes into the target, which has no media
+    assert!(run(Some("--index=txt")).status.success());
+    assert!(run(Some("--index")).status.success());
+    assert!(!dir.path().join("index.txt").exists());
+    assert!(dir.path().join("index.m3u").exists());
+
+    assert!(run(None).status.success());
+    assert!(!dir.path().join("index.m3u").exists());
+}
+
+#[test]
+fn test_foreign_index_kept() {
+    let dir = TempDir::new().unwrap();
+    create_test_structure(dir.path());
+    let curated = dir.path().join("index.m3u");
+    fs::write(&curated, "# my curated list\npsx/Final Fantasy VII/Final Fantasy VII.m3u\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg(dir.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    assert!(output.status.success(), "Command failed: {:?}", output);
+
+    // A hand-written index.m3u is not one of ours, so it survives the run
+    let content = fs::read_to_string(&curated).unwrap();
+    assert!(content.starts_with("# my curated list"));
+}
+
+#[test]
+fn test_index_name_collision() {
+    let dir = TempDir::new().unwrap();
+    let dest = TempDir::new().unwrap();
+    File::create(dir.path().join("index.cue")).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_m3u-emu"))
+        .arg("--index")
+        .arg(dir.path())
+        .arg(dest.path())
+        .output()
+        .expect("Failed to run m3u-emu");
+
+    // The game's playlist is kept and the run fails instead of replacing it
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("refusing to overwrite"), "stderr: {}", stderr);
+    let content = fs::read_to_string(dest.path().join("index.m3u")).unwrap();
+    assert!(content.contains("index.cue"));
+}